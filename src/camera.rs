@@ -1,21 +1,36 @@
 use bevy::prelude::*;
 
-use crate::{characters::player::Player, crowd::CrowdMember};
+use crate::{characters::player::Player, crowd::CrowdMember, soul_shift::SoulSpirit};
 
 /// The camera follows the current controled entity
 ///
-/// # Note
+/// During a Soul Shift, the camera follows the soul
+/// from the corpse to the new body.
 ///
-/// IDEA: gamefeel - smooth transition between mind control switch
+/// In versus, it stays between the two players, until a soul leaves its body.
 pub fn camera_follow(
     player_query: Query<&Transform, (With<Player>, Without<CrowdMember>)>,
+    soul_query: Query<&Transform, (With<SoulSpirit>, Without<Camera>)>,
     mut camera_query: Query<&mut Transform, (Without<Player>, With<Camera>)>,
 ) {
-    let player_transform = player_query.single();
-    let mut camera_transform = camera_query.single_mut();
+    let target = match (middle(soul_query.iter()), middle(player_query.iter())) {
+        (Some(soul), _) => soul,
+        (None, Some(player)) => player,
+        // No one to follow yet
+        (None, None) => return,
+    };
+    let mut camera_transform = match camera_query.get_single_mut() {
+        Ok(camera_transform) => camera_transform,
+        // e.g. headless
+        Err(_) => return,
+    };
 
-    camera_transform.translation.x = camera_transform
-        .translation
-        .lerp(player_transform.translation, 0.5)
-        .x;
+    camera_transform.translation.x = camera_transform.translation.lerp(target, 0.5).x;
+}
+
+fn middle<'a>(transforms: impl Iterator<Item = &'a Transform>) -> Option<Vec3> {
+    let (sum, count) = transforms.fold((Vec3::ZERO, 0), |(sum, count), transform| {
+        (sum + transform.translation, count + 1)
+    });
+    (count > 0).then(|| sum / count as f32)
 }
@@ -1,18 +1,24 @@
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
+use serde::Deserialize;
 
 use crate::{
-    // collisions::CollisionEventExt,
+    collisions::CollisionEventExt,
     characters::{
-        animations::CharacterState,
+        animations::{animate_character, jump_frame_player_state, CharacterState, SpriteAnimations},
         movement::CharacterHitbox,
         npcs::boss::Boss,
         player::Player,
-    }, soul_shift::{SoulShiftEvent, SoulShifting, start_soul_shift}, crowd::CrowdMember
+        state_machine::ChangeStateEvent,
+    }, soul_shift::{PossessionEvent, SoulShiftEvent, SoulShifting, start_soul_shift}, crowd::CrowdMember,
+    constants::character::{
+        player::{COMBO_DAMAGE_SCALING, COMBO_MIN_DAMAGE_SCALE},
+        CHARGED_ATTACK_STAGGER_MULTIPLIER, INVULNERABLE_ALPHA, INVULNERABLE_BLINK_PERIOD,
+    },
+    combat_log::{CombatEvent, CombatLogger},
+    simulation::GameplayApp,
 };
 
-use super::npcs::boss::BossAttack;
-
 pub struct AggressionPlugin;
 
 impl Plugin for AggressionPlugin {
@@ -20,29 +26,36 @@ impl Plugin for AggressionPlugin {
     fn build(&self, app: &mut App) {
         app 
             // -- Aesthetic --
-            .add_event::<FlipAttackSensorEvent>()
-            .add_system(flip_attack_sensor)
-            .add_system(charged_attack)
-            .add_system(damage_animation.label("Damage Animation").after("Damage Hit"))
+            .add_gameplay_event::<FlipAttackSensorEvent>()
+            .add_gameplay_system(flip_attack_sensor)
+            .add_gameplay_system(charged_attack)
             // -- ? --
-            .add_event::<DamageHitEvent>()
-            .add_system(invulnerability_timer.label("Invulnerability Timer"))
-            .add_system(cooldown_timer.label("Cooldown Timer"))
-            .add_system(player_attack_hitbox_activation.label("Player Attack Hitbox Activation"))
-            .add_system(
+            .add_gameplay_event::<DamageHitEvent>()
+            .add_gameplay_event::<DamageDealtEvent>()
+            .add_gameplay_system(invulnerability_timer.label("Invulnerability Timer"))
+            .add_gameplay_system(invulnerability_hint.before("Invulnerability Timer"))
+            .add_gameplay_system(parry_timer)
+            .add_gameplay_system(poise_regeneration)
+            .add_gameplay_system(cooldown_timer.label("Cooldown Timer"))
+            .add_gameplay_system(
+                attack_hitbox_activation
+                    .label("Attack Hitbox Activation")
+                    .after(animate_character)
+                    .after(jump_frame_player_state)
+            )
+            // Reads the intersections of the last physics step:
+            // the hitboxes activated this frame are only met at the next one
+            .add_gameplay_system(
                 attack_collision
                     .label("Attack Collision")
-                    .after("Player Attack Hitbox Activation")
-                    .after("Boss Attack Hitbox Activation")
                     .after(start_soul_shift)
             )
-            .add_system(bam_the_player.label("Bam The Player"))
-            .add_system(
+            .add_gameplay_system(
                 damage_hit
                     .label("Damage Hit")
                     .after(start_soul_shift)
-                    .after("Bam The Player")
                     .after("Attack Collision")
+                    .before("Change State")
             )
             ;
     }
@@ -57,27 +70,168 @@ pub struct AttackCharge {
     pub timer: Timer,
 }
 
+/// Light attacks chained one after another.
+///
+/// See `animations::ComboChain` and `characters::player::player_combo`
+#[derive(Component, Debug, Default)]
+pub struct Combo {
+    /// Swings chained since the first one
+    pub count: i32,
+    /// Attack was pressed during the window of the current swing
+    pub chained: bool,
+    /// The current swing could lead to another one:
+    /// ending it unchained drops the combo
+    pub open: bool,
+}
+
+impl Combo {
+    /// The longer the combo, the weaker the swings.
+    pub fn damage_scale(&self) -> f32 {
+        COMBO_DAMAGE_SCALING.powi(self.count).max(COMBO_MIN_DAMAGE_SCALE)
+    }
+}
+
+/// Dropped the combo: can't move nor attack until the end of the timer.
+#[derive(Component, Deref, DerefMut)]
+pub struct ComboPenalty(pub Timer);
+
 pub struct FlipAttackSensor(pub Entity);
 
 /// Contains the damage it deals
 #[derive(Component)]
 pub struct AttackHitbox(pub i32);
 
+/// The hurtboxes an attack hitbox meets while active.
+///
+/// Inactive, it meets no one (see `collisions::attack_hitbox_groups`).
+#[derive(Component, Deref, DerefMut, Clone, Copy)]
+pub struct AttackTargets(pub Group);
+
+/// The hurtboxes overlapped by an active attack hitbox,
+/// and the ones it already hit: once per activation.
+///
+/// Cleared when the hitbox is deactivated, see `attack_collision`
+#[derive(Component, Default)]
+pub struct AttackContacts {
+    touching: Vec<Entity>,
+    hit: Vec<Entity>,
+}
+
+/// Which part of the attack this hitbox is.
+///
+/// Used to activate it on the right frames, see `animations::AnimationClip`
+#[derive(Component, Clone, Copy, Debug, Deserialize, PartialEq, Eq, Hash)]
+pub enum AttackHitboxKind {
+    // -- Player --
+    BottomWhip,
+    FrontBall,
+    // -- Boss --
+    Smash,
+    FallenAngel,
+}
+
 #[derive(Component, Deref, DerefMut)]
 pub struct Invulnerable(pub Timer);
 
+/// The hits are deflected until the end of the timer
+#[derive(Component, Deref, DerefMut)]
+pub struct Parrying(pub Timer);
+
 #[derive(Component, Deref, DerefMut)]
 pub struct AttackCooldown(pub Timer);
 
 #[derive(Component)]
 pub struct DeadBody;
 
+/// Can't die: a lethal hit refills the `Hp` instead (training)
+#[derive(Component)]
+pub struct Undying;
+
 #[derive(Component)]
 pub struct Hp {
     pub current: i32,
     pub max: i32,
 }
 
+/// Hits accumulate stagger,
+/// only a broken poise interrupts the character (`CharacterState::Stunned`).
+///
+/// See `HitReaction::Poise`
+#[derive(Component, Debug)]
+pub struct Poise {
+    pub current: f32,
+    pub max: f32,
+    pub regen: f32,
+    /// Regeneration starts when it finishes
+    pub regen_delay: Timer,
+}
+
+impl Poise {
+    pub fn new(max: f32, regen: f32, regen_delay: f32) -> Poise {
+        Poise {
+            current: max,
+            max,
+            regen,
+            regen_delay: Timer::from_seconds(regen_delay, TimerMode::Once),
+        }
+    }
+
+    /// Is the poise broken by this stagger ?
+    ///
+    /// A broken poise is fully restored.
+    pub fn stagger(&mut self, stagger: f32) -> bool {
+        self.current -= stagger;
+        self.regen_delay.reset();
+
+        if self.current <= 0. {
+            self.current = self.max;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// How a character takes the hits, consulted by `damage_hit`.
+#[derive(Component, Debug, Clone)]
+pub struct DamagePolicy {
+    /// Invulnerability after a hit, in seconds
+    pub invulnerability: f32,
+    pub hit_reaction: HitReaction,
+    /// Damage absorbed from each hit
+    pub armor: i32,
+    /// Multiplies the damage taken, after the armor
+    pub damage_multiplier: f32,
+}
+
+/// Previous behavior: long invulnerability, interrupted by any hit
+impl Default for DamagePolicy {
+    fn default() -> Self {
+        DamagePolicy {
+            invulnerability: 2.,
+            hit_reaction: HitReaction::Flinch,
+            armor: 0,
+            damage_multiplier: 1.,
+        }
+    }
+}
+
+impl DamagePolicy {
+    pub fn damage_taken(&self, damage: i32) -> i32 {
+        ((damage - self.armor).max(0) as f32 * self.damage_multiplier).round() as i32
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HitReaction {
+    /// Every hit interrupts (`CharacterState::Hit`)
+    Flinch,
+    /// Only when the `Poise` breaks (`CharacterState::Stunned`)
+    Poise,
+    /// Never interrupted
+    SuperArmor,
+}
+
 impl Hp {
     pub fn default() -> Hp {
         Hp { current: 100, max: 100 }
@@ -86,6 +240,15 @@ impl Hp {
     pub fn new(max: i32) -> Hp {
         Hp { current: max, max }
     }
+
+    /// Keeps the damage taken, but can't kill nor revive.
+    pub fn set_max(&mut self, max: i32) {
+        let damage_taken = self.max - self.current;
+        self.max = max;
+        if self.current > 0 {
+            self.current = (max - damage_taken).clamp(1, max);
+        }
+    }
 }
 
 /// Happens when
@@ -97,13 +260,32 @@ impl Hp {
 ///   - characters::aggression::damage_hit
 ///     - Target gets reckted (lower hp).
 ///     Death if it was too much
+#[derive(Debug, Clone)]
 pub struct DamageHitEvent {
     /// Where the damage is stored
     pub attack_hitbox: Entity,
+    pub attacker: Entity,
     /// Entity getting hit
     pub target: Entity,
 }
 
+/// Happens when
+///   - characters::aggression::damage_hit
+///     - The hit landed on a vulnerable target
+/// 
+/// Read in
+///   - game_feel::impact_feedback
+///     - Hit-stop, screen shake, flash and sparks
+///   - training::spawn_damage_numbers
+///     - The damage pops above the target
+#[derive(Debug, Clone)]
+pub struct DamageDealtEvent {
+    pub attack_hitbox: Entity,
+    pub target: Entity,
+    /// Actually taken by the target
+    pub damage: i32,
+}
+
 /// Happens when
 ///   - characters::player::player_movement
 ///     - The player turns into opposite direction
@@ -150,18 +332,22 @@ fn flip_attack_sensor(
     }
 }
 
-/// Lower the cooldown timer and remove it when it fishied
+/// Lower the invulnerability timer and remove it when it fishied
+/// (with its hint)
 fn invulnerability_timer(
     mut commands: Commands,
     time: Res<Time>,
     
-    // The Boss has their cooldown in their Attack Range Sensor
-    mut invulnerable_character: Query<(Entity, &mut Invulnerable)>,
+    mut invulnerable_character: Query<(Entity, &mut Invulnerable, Option<&mut TextureAtlasSprite>)>,
 ) {
-    for (character, mut invulnerability) in invulnerable_character.iter_mut() {
+    for (character, mut invulnerability, sprite) in invulnerable_character.iter_mut() {
         invulnerability.tick(time.delta());
 
         if invulnerability.just_finished() {
+            if let Some(mut sprite) = sprite {
+                sprite.color.set_a(1.);
+            }
+
             commands
                 .entity(character)
                 .remove::<Invulnerable>();
@@ -169,6 +355,48 @@ fn invulnerability_timer(
     }
 }
 
+fn parry_timer(
+    mut commands: Commands,
+    time: Res<Time>,
+
+    mut parrying_character: Query<(Entity, &mut Parrying)>,
+) {
+    for (character, mut parry) in parrying_character.iter_mut() {
+        parry.tick(time.delta());
+
+        if parry.just_finished() {
+            commands.entity(character).remove::<Parrying>();
+        }
+    }
+}
+
+fn poise_regeneration(time: Res<Time>, mut poise_query: Query<&mut Poise>) {
+    for mut poise in poise_query.iter_mut() {
+        poise.regen_delay.tick(time.delta());
+
+        if poise.regen_delay.finished() && poise.current < poise.max {
+            poise.current = (poise.current + poise.regen * time.delta_seconds()).min(poise.max);
+        }
+    }
+}
+
+/// Invulnerable characters blink, so we know when they can be hurt.
+fn invulnerability_hint(
+    mut invulnerable_character: Query<(&Invulnerable, &mut TextureAtlasSprite), Without<DeadBody>>,
+) {
+    for (invulnerability, mut sprite) in invulnerable_character.iter_mut() {
+        // Transparent during the first half of each blink
+        let blink_progress = (invulnerability.elapsed_secs() / INVULNERABLE_BLINK_PERIOD).fract();
+        let alpha = if blink_progress < 0.5 {
+            INVULNERABLE_ALPHA
+        } else {
+            1.
+        };
+
+        sprite.color.set_a(alpha);
+    }
+}
+
 /// Lower the cooldown timer and remove it when it fishied
 fn cooldown_timer(
     mut commands: Commands,
@@ -188,115 +416,145 @@ fn cooldown_timer(
     }
 }
 
-/// Activate when the character is on animation phase Attack,
+/// Activate the attack hitboxes only on the frames declared in the animation clip,
 /// Deactivate else.
-fn player_attack_hitbox_activation(
+///
+/// An active hitbox meets its `AttackTargets`:
+/// the intersections starting right now, even with an already overlapping hurtbox,
+/// are reported to `attack_collision`.
+fn attack_hitbox_activation(
     mut commands: Commands,
     
-    player_query: Query<
+    sprite_animations: Res<Assets<SpriteAnimations>>,
+    character_query: Query<
         (
             &CharacterState,
+            &TextureAtlasSprite,
+            &Handle<SpriteAnimations>,
             &Children,
-            &Name,
         ),
-        (Changed<CharacterState>, With<Player>),
+        Or<(Changed<CharacterState>, Changed<TextureAtlasSprite>)>,
+    >,
+    parent_hitbox_position_query: Query<&Children, With<AttackSensor>>,
+    mut attack_hitbox_query: Query<
+        (&AttackHitboxKind, &AttackTargets, &mut CollisionGroups, Option<&ActiveEvents>),
+        (With<AttackHitbox>, With<Sensor>),
     >,
-    parent_hitbox_position_query: Query<(Entity, &Children), With<AttackSensor>>,
-    // attack_hitbox_query: Query<Entity, (With<AttackHitbox>, With<Sensor>)>
 ) {
-    for (character_state, children, _name) in player_query.iter() {
-        // info!("DEBUG: {} Changed {:?}", name, *character_state);
+    for (character_state, sprite, animations_handle, children) in character_query.iter() {
+        let clip = sprite_animations
+            .get(animations_handle)
+            .and_then(|animations| animations.clips.get(character_state));
+
         for child in children.iter() {
             match parent_hitbox_position_query.get(*child) {
                 Err(_) => continue,
                 // The parent Hitbox contains the modifiable transform
                 // for all their hitbox children
-                Ok((_parent_hitbox, hitbox_children)) => {
+                Ok(hitbox_children) => {
                     for hitbox_child in hitbox_children.iter() {
-                        // OPTIMIZE: Hitbox Activation - This statement will be called a bit too much
-                        // vv-- to just see uncomment the two DEBUG info below --vv
-                        if *character_state == CharacterState::Attack
-                        || *character_state == CharacterState::SecondAttack
-                        || *character_state == CharacterState::ChargedAttack
-                        {
-                            // info!("DEBUG: Inserted on {}", _name);
-                            commands.entity(*hitbox_child).insert(ActiveEvents::COLLISION_EVENTS);
-                        } else {
-                            // info!("DEBUG: Removed on {}", _name);
-                            commands.entity(*hitbox_child).remove::<ActiveEvents>();
+                        match attack_hitbox_query.get_mut(*hitbox_child) {
+                            Err(_) => warn!("Non Indexed Attack Hitbox"),
+                            Ok((kind, targets, mut groups, active_events)) => {
+                                // A state without clip never activates any hitbox
+                                let active = clip.is_some_and(|clip| clip.is_hitbox_active(*kind, sprite.index));
+
+                                // Only touch the hitbox when its activation changes
+                                if active && active_events.is_none() {
+                                    groups.filters = **targets;
+                                    commands.entity(*hitbox_child).insert(ActiveEvents::COLLISION_EVENTS);
+                                } else if !active && active_events.is_some() {
+                                    groups.filters = Group::NONE;
+                                    commands.entity(*hitbox_child).remove::<ActiveEvents>();
+                                }
+                            }
                         }
-                        // match attack_hitbox_query.get(*child)
                     }
                 }
             }
-            
         }
     }
 }
 
+/// Detected non self-harm touches.
+///
+/// The intersections started and stopped are tracked for each active hitbox:
+/// the collision groups already discarded the pairs which can't hurt.
+/// A hurtbox is hit as soon as its character is vulnerable,
+/// even if the overlap started during their invulnerability or their parry.
+fn attack_collision(
+    mut collision_events: EventReader<CollisionEvent>,
+
+    mut attack_hitbox_query: Query<(Entity, &Parent, &mut AttackContacts, Option<&ActiveEvents>), (With<Sensor>, With<AttackHitbox>)>,
+    character_hitbox_query: Query<&Parent, With<CharacterHitbox>>,
 
-fn bam_the_player(
-    keyboard_input: Res<Input<KeyCode>>,
-    player_query: Query<Entity, (With<Player>, Without<Invulnerable>)>,
-    boss_attack_hitbox: Query<Entity, (With<BossAttack>, With<AttackHitbox>)>,
+    target_query: Query<Entity, (Without<Invulnerable>, Without<Parrying>, Without<SoulShifting>, Without<DeadBody>)>,
+    
+    // vv-- They has as child a attackHitbox which inherit their transform
+    attack_sensor_query: Query<(Entity, &Parent), With<AttackSensor>>,
 
     mut damage_hit_event: EventWriter<DamageHitEvent>,
 ) {
-    if keyboard_input.just_pressed(KeyCode::Space) {
-        if let Ok(player) = player_query.get_single() {
-            info!("Bam dans ta gueule !");
-            for attack_hitbox in boss_attack_hitbox.iter() {
-                damage_hit_event.send(DamageHitEvent {
-                    attack_hitbox,
-                    target: player
-                });
-                break;
+    for collision_event in collision_events.iter() {
+        let (e1, e2) = collision_event.entities();
+        // The pair comes in any order
+        let (attack_hitbox, hurtbox) = if attack_hitbox_query.contains(e1) && character_hitbox_query.contains(e2) {
+            (e1, e2)
+        } else if attack_hitbox_query.contains(e2) && character_hitbox_query.contains(e1) {
+            (e2, e1)
+        } else {
+            // e.g. the boss range sensor
+            continue;
+        };
+
+        if let Ok((_, _, mut contacts, _)) = attack_hitbox_query.get_mut(attack_hitbox) {
+            if collision_event.is_started() {
+                if !contacts.touching.contains(&hurtbox) {
+                    contacts.touching.push(hurtbox);
+                }
+            } else {
+                contacts.touching.retain(|touching| *touching != hurtbox);
             }
         }
     }
-}
 
-// REFACTOR: ALL AGGRESSION COLLISION
-
-/// Detected non self-harm touches.
-fn attack_collision(
-    // mut collision_events: EventReader<CollisionEvent>,
-    rapier_context: Res<RapierContext>,
-    
-    attack_hitbox_query: Query<(Entity, &Parent), (With<Sensor>, With<AttackHitbox>, With<ActiveEvents>)>,
-    character_hitbox_query: Query<(Entity, &Parent), With<CharacterHitbox>>,
+    for (attack_hitbox, parent_hitbox, mut contacts, active_events) in attack_hitbox_query.iter_mut() {
+        // Deactivated: the next activation hits again
+        if active_events.is_none() {
+            if !contacts.touching.is_empty() || !contacts.hit.is_empty() {
+                *contacts = AttackContacts::default();
+            }
+            continue;
+        }
 
-    target_query: Query<Entity, (Without<Invulnerable>, Without<SoulShifting>)>,
-    
-    // vv-- They has as child a attackHitbox which inherit their transform
-    attack_sensor_query: Query<(Entity, &Parent), With<AttackSensor>>,
+        let attacker = match attack_sensor_query.get(**parent_hitbox) {
+            Err(e) => {
+                warn!("The attackHitbox's hierarchy is invalid: {:?}", e);
+                continue;
+            }
+            Ok((_, attacker)) => **attacker,
+        };
 
-    mut damage_hit_event: EventWriter<DamageHitEvent>,
-) {
-    // OPTIMIZE: Querying all attack hitbox then all character hitbox is not very efficient
-    for (attack_hitbox_entity, parent_hitbox) in attack_hitbox_query.iter() {
-        for (character_hitbox, target) in character_hitbox_query.iter() {
-            match target_query.get(**target) {
-                // The target is invulnerable
+        for hurtbox in contacts.touching.clone() {
+            if contacts.hit.contains(&hurtbox) {
+                continue;
+            }
+            let target = match character_hitbox_query.get(hurtbox) {
                 Err(_) => continue,
-                Ok(_) => {
-                    if rapier_context.intersection_pair(attack_hitbox_entity, character_hitbox) == Some(true) {
-                        match attack_sensor_query.get(**parent_hitbox) {
-                            Err(e) => warn!("The attackHitbox's hierarchy is invalid: {:?}", e),
-                            Ok((_, attacker)) => {
-                                if **attacker != **target {
-                                    damage_hit_event.send(DamageHitEvent {
-                                        attack_hitbox: attack_hitbox_entity,
-                                        target: **target
-                                    });
-                                    info!("Damage Hit Event !");
-                                }
-                            }
-                        }
-                    }
-    
-                }
+                Ok(target) => **target,
+            };
+
+            // The target is invulnerable, for now
+            if attacker == target || !target_query.contains(target) {
+                continue;
             }
+
+            contacts.hit.push(hurtbox);
+            damage_hit_event.send(DamageHitEvent {
+                attack_hitbox,
+                attacker,
+                target
+            });
         }
     }
 }
@@ -304,11 +562,9 @@ fn attack_collision(
 /// Inflicts Damage (contains within the attack hitbox) to the touched entity.
 /// 
 /// Send a ~~Death Event~~ Soul Shift Event if it's too much...
+/// or a Possession Event when the target is not a player.
 /// 
-/// # Note
-/// 
-/// TODO: Lower the Invulnerability post hit of the boss
-/// ^^^^^---- Allow Spamming Attack and Slap Return
+/// How the target takes it depends on its `DamagePolicy`.
 fn damage_hit(
     mut damage_hit_event: EventReader<DamageHitEvent>,
     
@@ -316,59 +572,122 @@ fn damage_hit(
     
     // With<ActiveEvents>
     attack_hitbox_query: Query<&AttackHitbox, With<Sensor>>,
-    mut target_query: Query<&mut Hp, (Without<Invulnerable>, Without<SoulShifting>, Without<CrowdMember>)>,
+    mut target_query: Query<(&mut Hp, Option<&DamagePolicy>, Option<&mut Poise>, Option<&Player>, Option<&Undying>), (Without<Invulnerable>, Without<Parrying>, Without<SoulShifting>, Without<CrowdMember>)>,
     
-    player_query: Query<Entity, With<Player>>,
+    attacker_query: Query<(Option<&Combo>, Option<&CharacterState>)>,
 
     mut soul_shift_event: EventWriter<SoulShiftEvent>,
+    mut possession_event: EventWriter<PossessionEvent>,
+    mut change_state_event: EventWriter<ChangeStateEvent>,
+    mut damage_dealt_event: EventWriter<DamageDealtEvent>,
+    mut combat_log: CombatLogger,
 ) {
-    for DamageHitEvent {attack_hitbox, target} in damage_hit_event.iter() {
-        // There is much of it ----vvvv
-        // info!("Damage Hit Event !");
+    // Made invulnerable or brought down by a previous hit of this run:
+    // the commands, and thus `Invulnerable`, are only applied after it
+    let mut out_of_reach: Vec<Entity> = Vec::new();
+
+    for DamageHitEvent {attack_hitbox, attacker, target} in damage_hit_event.iter() {
+        // Both hitboxes of a Smash are active on the same frames
+        if out_of_reach.contains(target) {
+            continue;
+        }
+
         match (attack_hitbox_query.get(*attack_hitbox), target_query.get_mut(*target)) {
             // Invulnerable or SoulShifting target
             (Ok(_),Err(_)) => continue,
             // Invalid Attacker
             (Err(e),_) => warn!("Problem {:?}", e),
-            (Ok(attack_damage), Ok(mut hp)) => {
+            (Ok(attack_damage), Ok((mut hp, damage_policy, poise, player, undying))) => {
+                let damage_policy = damage_policy.cloned().unwrap_or_default();
+                let (combo, attacker_state) = attacker_query.get(*attacker).unwrap_or((None, None));
+
+                let damage = match combo {
+                    None => attack_damage.0,
+                    Some(combo) => {
+                        let scaled_damage = attack_damage.0 as f32 * combo.damage_scale();
+                        (scaled_damage.round() as i32).max(1)
+                    }
+                };
+                let damage = damage_policy.damage_taken(damage);
+                let hp_after = if damage <= 0 {
+                    hp.current
+                } else if hp.current > damage {
+                    hp.current - damage
+                } else if undying.is_some() {
+                    // Refilled instead
+                    hp.max
+                } else {
+                    0
+                };
+                combat_log.log(CombatEvent::Hit {
+                    attacker: *attacker,
+                    target: *target,
+                    hitbox: *attack_hitbox,
+                    damage,
+                    hp_before: hp.current,
+                    hp_after,
+                });
+                // Fully absorbed
+                if damage <= 0 {
+                    continue;
+                }
 
-                // info!("Damage Hit Event To a Vulnerable target!");
-                if hp.current <= attack_damage.0 {
-                    hp.current = 0;
-                    info!("Lethal Damage!");
+                damage_dealt_event.send(DamageDealtEvent {
+                    attack_hitbox: *attack_hitbox,
+                    target: *target,
+                    damage,
+                });
+                // Only when hurt: no hit animation while healing
+                let hit_reaction = match (damage_policy.hit_reaction, poise) {
+                    (HitReaction::Flinch, _) => Some(CharacterState::Hit),
+                    (HitReaction::SuperArmor, _) | (HitReaction::Poise, None) => None,
+                    (HitReaction::Poise, Some(mut poise)) => {
+                        let stagger = match attacker_state {
+                            Some(CharacterState::ChargedAttack) => {
+                                damage as f32 * CHARGED_ATTACK_STAGGER_MULTIPLIER
+                            }
+                            _ => damage as f32,
+                        };
+                        // Otherwise, the hit is tanked
+                        poise.stagger(stagger).then_some(CharacterState::Stunned)
+                    }
+                };
+                if let Some(state) = hit_reaction {
+                    change_state_event.send(ChangeStateEvent {
+                        entity: *target,
+                        state,
+                    });
+                }
 
-                    match player_query.get(*target) {
-                        Err(_) => {
-                            // TODO: Boss Death Event
+                hp.current = hp_after;
+                if hp_after == 0 {
+                    out_of_reach.push(*target);
+
+                    match player {
+                        None => {
+                            // Only the boss can be taken, see `soul_shift::possess_the_boss`
+                            possession_event.send(PossessionEvent {
+                                body: *target,
+                                by: *attacker,
+                            });
                         }
-                        Ok(_) => {
+                        Some(_) => {
                             // TODO: send Player Death Event when the player die
                             // atm all dying entity will trigger the soul shift/kill the player
                             // commands.entity(*target).insert(SoulShifting);
                             soul_shift_event.send(SoulShiftEvent(*target));
                         }
                     }
-                } else {
-                    hp.current -= attack_damage.0;
-                    // TODO: Seperate player and boss gestion of getting hit
-                    // TODO: Invulnerable Hint
+                } else if damage_policy.invulnerability > 0. {
+                    out_of_reach.push(*target);
                     commands
                         .entity(*target)
-                        .insert(Invulnerable(Timer::from_seconds(2., TimerMode::Once)));
+                        .insert(Invulnerable(Timer::from_seconds(
+                            damage_policy.invulnerability,
+                            TimerMode::Once,
+                        )));
                 }
             }
         }   
     }
 }
-
-/// Change the Animation to Hit when being hurted.
-/// TODO: Prevent hit anim while healing
-/// Carefull: Even if the Hp is rising this animation will trigger
-fn damage_animation(
-    // DEBUG: Crowd getting hit (maybe the prb is here)
-    mut bleeding_character_query: Query<(Entity, &mut CharacterState), Changed<Hp>>,
-) {
-    for (_hurted_character, mut character_state) in bleeding_character_query.iter_mut() {
-        *character_state = CharacterState::Hit;
-    }
-}
@@ -1,12 +1,35 @@
-use bevy::{prelude::*, utils::HashMap};
+//! Sprite Animations
+//!
+//! The animations of a character are defined in a `.anim.ron` asset:
+//! its spritesheet, the grid, and a clip for each `CharacterState`
+//! (frames, duration of each frame, what happens at the end, hitboxes and frame events).
+//!
+//! The spritesheet is loaded with it as the labeled asset `#atlas`.
+
+use std::path::PathBuf;
+
+use bevy::{
+    asset::{AssetLoader, AssetPath, BoxedFuture, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+    utils::HashMap,
+};
 use bevy_inspector_egui::Inspectable;
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    characters::{aggression::DeadBody, npcs::boss::Boss, player::Player},
+    characters::{
+        aggression::{AttackHitboxKind, DeadBody},
+        npcs::boss::Boss,
+        player::Player,
+    },
+    constants::character::FRAME_TIME,
     crowd::CrowdMember,
 };
 
-#[derive(Default, Debug, Clone, Component, Eq, Hash, Inspectable, PartialEq)]
+#[derive(
+    Default, Debug, Clone, Component, Deserialize, Serialize, Eq, Hash, Inspectable, PartialEq,
+)]
 pub enum CharacterState {
     #[default]
     Idle,
@@ -17,17 +40,266 @@ pub enum CharacterState {
     Charge,
     Run,
     Hit,
+    /// Poise broken: longer than Hit
+    Stunned,
     Dead,
     // OPTIMIZE: Stop animate
     // PermaDeath,
 }
 
+impl CharacterState {
+    /// Every state, each one must have a clip
+    pub const ALL: [CharacterState; 10] = [
+        CharacterState::Idle,
+        CharacterState::Attack,
+        CharacterState::SecondAttack,
+        CharacterState::ChargedAttack,
+        CharacterState::TransitionToCharge,
+        CharacterState::Charge,
+        CharacterState::Run,
+        CharacterState::Hit,
+        CharacterState::Stunned,
+        CharacterState::Dead,
+    ];
+}
+
 #[derive(Component, Deref, DerefMut)]
 pub struct AnimationTimer(pub Timer);
 
-#[derive(Component, Deref, DerefMut, Clone)]
-pub struct AnimationIndices(pub HashMap<CharacterState, (usize, usize)>);
+/// Multiplies the speed of all clips (crowd variety)
+#[derive(Component, Deref, DerefMut)]
+pub struct PlaybackSpeed(pub f32);
+
+/// All the animations of a character.
+#[derive(Debug, Deserialize, TypeUuid)]
+#[uuid = "5b2e5a0c-8f0f-4d3c-9d55-2b6d4f0e7a11"]
+pub struct SpriteAnimations {
+    /// Path of the spritesheet, from the assets folder
+    pub sheet: String,
+    pub tile_size: (f32, f32),
+    pub columns: usize,
+    pub rows: usize,
+    pub clips: HashMap<CharacterState, AnimationClip>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AnimationClip {
+    /// First and last frame (included) in the spritesheet
+    pub frames: (usize, usize),
+    /// Default duration of a frame
+    #[serde(default = "default_frame_duration")]
+    pub frame_duration: f32,
+    /// Frames lasting longer (or shorter) than `frame_duration`
+    #[serde(default)]
+    pub durations: HashMap<usize, f32>,
+    #[serde(default)]
+    pub end: ClipEnd,
+    /// First frame from which the clip can be interrupted
+    /// by a state with a lower or equal priority (see `state_machine`).
+    ///
+    /// Without it, the clip can always be interrupted.
+    #[serde(default)]
+    pub recovery: Option<usize>,
+    /// Attack pressed during this window chains into another clip
+    #[serde(default)]
+    pub chain: Option<ComboChain>,
+    /// Attack hitboxes active during which frames (included)
+    #[serde(default)]
+    pub hitboxes: Vec<(AttackHitboxKind, (usize, usize))>,
+    /// Named events sent when entering a frame (`"footstep"`, `"sfx:swing"`, ...)
+    #[serde(default)]
+    pub events: HashMap<usize, Vec<String>>,
+}
+
+fn default_frame_duration() -> f32 {
+    FRAME_TIME
+}
+
+/// The next swing of a combo
+#[derive(Debug, Clone, Deserialize)]
+pub struct ComboChain {
+    pub next: CharacterState,
+    /// First and last frame (included) where attack can be pressed
+    pub window: (usize, usize),
+}
+
+impl ComboChain {
+    pub fn is_open(&self, frame: usize) -> bool {
+        self.window.0 <= frame && frame <= self.window.1
+    }
+}
+
+/// What happens after the last frame of a clip
+#[derive(Debug, Default, Clone, Deserialize)]
+pub enum ClipEnd {
+    #[default]
+    Loop,
+    Next(CharacterState),
+    /// Stay on the last frame and stop animating
+    Hold,
+}
+
+impl AnimationClip {
+    pub fn frame_duration(&self, frame: usize) -> f32 {
+        *self.durations.get(&frame).unwrap_or(&self.frame_duration)
+    }
+
+    /// Has the clip reached its recovery frame ?
+    pub fn is_recovered(&self, frame: usize) -> bool {
+        match self.recovery {
+            None => true,
+            Some(recovery) => frame >= recovery,
+        }
+    }
+
+    fn contains(&self, frame: usize) -> bool {
+        self.frames.0 <= frame && frame <= self.frames.1
+    }
+
+    /// Is this hitbox active at this frame ?
+    pub fn is_hitbox_active(&self, kind: AttackHitboxKind, frame: usize) -> bool {
+        self.hitboxes.iter().any(|(window_kind, (first, last))| {
+            *window_kind == kind && *first <= frame && frame <= *last
+        })
+    }
+}
+
+impl SpriteAnimations {
+    /// Is the character stuck in its current clip,
+    /// not yet in its recovery frames ?
+    pub fn is_locked(&self, state: &CharacterState, frame: usize) -> bool {
+        match self.clips.get(state) {
+            None => false,
+            Some(clip) => !clip.is_recovered(frame),
+        }
+    }
+
+    /// Every state has frames in the sheet,
+    /// and every frame mentioned in a clip belongs to it.
+    pub fn validate(&self) -> Result<(), SpriteAnimationsError> {
+        for state in CharacterState::ALL {
+            let clip = match self.clips.get(&state) {
+                None => return Err(SpriteAnimationsError::MissingClip(state)),
+                Some(clip) => clip,
+            };
+
+            if clip.frames.0 > clip.frames.1 || clip.frames.1 >= self.columns * self.rows {
+                return Err(SpriteAnimationsError::OutOfSheet(state, clip.frames));
+            }
+
+            let clip_frames = clip
+                .durations
+                .keys()
+                .chain(clip.events.keys())
+                .chain(clip.recovery.iter())
+                .chain(
+                    clip.chain
+                        .iter()
+                        .flat_map(|chain| [&chain.window.0, &chain.window.1]),
+                )
+                .chain(
+                    clip.hitboxes
+                        .iter()
+                        .flat_map(|(_, (first, last))| [first, last]),
+                );
+
+            for frame in clip_frames {
+                if !clip.contains(*frame) {
+                    return Err(SpriteAnimationsError::OutOfClip(state, *frame));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub enum SpriteAnimationsError {
+    MissingClip(CharacterState),
+    /// The frames of the clip are not in the spritesheet
+    OutOfSheet(CharacterState, (usize, usize)),
+    /// A frame of the clip (event, hitbox, ...) is not in its frames
+    OutOfClip(CharacterState, usize),
+}
+
+impl std::fmt::Display for SpriteAnimationsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SpriteAnimationsError::MissingClip(state) => write!(f, "no clip for {:?}", state),
+            SpriteAnimationsError::OutOfSheet(state, frames) => {
+                write!(
+                    f,
+                    "the frames {:?} of {:?} are not in the sheet",
+                    frames, state
+                )
+            }
+            SpriteAnimationsError::OutOfClip(state, frame) => {
+                write!(f, "the frame {} is not in the clip {:?}", frame, state)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SpriteAnimationsError {}
 
+#[derive(Default)]
+pub struct SpriteAnimationsLoader;
+
+impl AssetLoader for SpriteAnimationsLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let animations: SpriteAnimations = ron::de::from_bytes(bytes)?;
+            animations.validate()?;
+
+            let sheet_path = AssetPath::new(PathBuf::from(&animations.sheet), None);
+            let texture_handle: Handle<Image> = load_context.get_handle(sheet_path.clone());
+            let texture_atlas = TextureAtlas::from_grid(
+                texture_handle,
+                Vec2::new(animations.tile_size.0, animations.tile_size.1),
+                animations.columns,
+                animations.rows,
+                None,
+                None,
+            );
+
+            load_context.set_labeled_asset(
+                "atlas",
+                LoadedAsset::new(texture_atlas).with_dependency(sheet_path),
+            );
+            load_context.set_default_asset(LoadedAsset::new(animations));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["anim.ron"]
+    }
+}
+
+/// Happens when
+///   - characters::animations::animate_character
+///     - A frame with named events is reached
+///   - characters::animations::jump_frame_player_state
+///     - A clip with named events on its first frame is started
+///
+/// Read in
+///   - Whoever needs to sync with the animation (sfx, footsteps, fx...)
+#[derive(Debug, Clone)]
+pub struct AnimationFrameEvent {
+    pub entity: Entity,
+    pub state: CharacterState,
+    pub frame: usize,
+    pub name: String,
+}
+
+/// Plays the clip of the current `CharacterState`.
+///
+/// At the end of the clip, follows its `ClipEnd`.
+///
 /// # Note
 ///
 /// TODO: longer animation of "getting hit"
@@ -35,62 +307,82 @@ pub fn animate_character(
     mut commands: Commands,
 
     time: Res<Time>,
+    sprite_animations: Res<Assets<SpriteAnimations>>,
     mut query: Query<
         (
             Entity,
-            &AnimationIndices,
+            &Handle<SpriteAnimations>,
             &mut AnimationTimer,
             &mut TextureAtlasSprite,
             &mut CharacterState,
+            Option<&PlaybackSpeed>,
         ),
         Or<(With<Player>, With<Boss>, With<CrowdMember>, With<DeadBody>)>,
     >,
+    mut frame_event: EventWriter<AnimationFrameEvent>,
 ) {
-    for (character, indices, mut timer, mut sprite, mut character_state) in &mut query {
+    for (character, animations_handle, mut timer, mut sprite, mut character_state, speed) in
+        &mut query
+    {
+        // Still loading
+        let animations = match sprite_animations.get(animations_handle) {
+            None => continue,
+            Some(animations) => animations,
+        };
+
         timer.tick(time.delta());
 
         if timer.just_finished() {
-            let current_indices = indices[&character_state];
-            let next_phase: Option<CharacterState>;
-
-            if *character_state == CharacterState::Run
-                || *character_state == CharacterState::Attack
-                || *character_state == CharacterState::SecondAttack
-                || *character_state == CharacterState::ChargedAttack
-                || *character_state == CharacterState::Hit
-            {
-                // TODO: longer animation of "getting hit"
-                // Idle when stop running/attacking/getting hit
-                next_phase = Some(CharacterState::Idle);
-            } else if *character_state == CharacterState::TransitionToCharge {
-                // Charging
-                next_phase = Some(CharacterState::Charge);
-            } else if *character_state == CharacterState::Dead {
-                // CharacterState::PermaDeath (last frame to last frame)
-                next_phase = Some(CharacterState::Dead);
-            } else {
-                // Loop
-                next_phase = None;
-            }
+            let current_clip = match animations.clips.get(&character_state) {
+                None => {
+                    warn!("No clip for {:?} on {:?}", *character_state, character);
+                    continue;
+                }
+                Some(clip) => clip,
+            };
 
-            if sprite.index == current_indices.1 {
-                // Final Frame of Death
-                if *character_state == CharacterState::Dead {
-                    commands.entity(character).remove::<AnimationTimer>();
-                } else {
-                    match next_phase {
-                        Some(new_state) => {
-                            sprite.index = indices[&new_state].0;
-                            // update state
-                            *character_state = new_state;
-                        }
-                        None => {
-                            sprite.index = current_indices.0;
+            let mut entered_clip = false;
+            if sprite.index == current_clip.frames.1 {
+                match &current_clip.end {
+                    // Final Frame of Death
+                    ClipEnd::Hold => {
+                        commands.entity(character).remove::<AnimationTimer>();
+                        continue;
+                    }
+                    ClipEnd::Loop => {
+                        sprite.index = current_clip.frames.0;
+                    }
+                    ClipEnd::Next(new_state) => {
+                        match animations.clips.get(new_state) {
+                            None => warn!("No clip for {:?} on {:?}", new_state, character),
+                            Some(next_clip) => sprite.index = next_clip.frames.0,
                         }
+                        // update state
+                        *character_state = new_state.clone();
+                        entered_clip = true;
                     }
                 }
             } else {
-                sprite.index = sprite.index + 1
+                sprite.index += 1;
+            }
+
+            // -- New Frame --
+            if let Some(clip) = animations.clips.get(&character_state) {
+                let speed = speed.map_or(1., |speed| **speed);
+                timer.set_duration(std::time::Duration::from_secs_f32(
+                    clip.frame_duration(sprite.index) / speed,
+                ));
+
+                // The first frame of the next clip is announced by jump_frame_player_state
+                if !entered_clip {
+                    send_frame_events(
+                        &mut frame_event,
+                        character,
+                        &character_state,
+                        clip,
+                        sprite.index,
+                    );
+                }
             }
         }
     }
@@ -98,15 +390,71 @@ pub fn animate_character(
 
 /// Anytime the CharacterState change,
 /// force the sprite to match this change.
+///
+/// The events of the first frame are sent here:
+/// `animate_character` only sends them when moving to a new frame.
 pub fn jump_frame_player_state(
+    sprite_animations: Res<Assets<SpriteAnimations>>,
     mut query: Query<
-        (&AnimationIndices, &mut TextureAtlasSprite, &CharacterState),
-        (Or<(With<Player>, With<Boss>)>, Changed<CharacterState>),
+        (
+            Entity,
+            &Handle<SpriteAnimations>,
+            &mut TextureAtlasSprite,
+            &CharacterState,
+            Option<&mut AnimationTimer>,
+            Option<&PlaybackSpeed>,
+        ),
+        (
+            Or<(With<Player>, With<Boss>, With<CrowdMember>, With<DeadBody>)>,
+            Changed<CharacterState>,
+        ),
     >,
+    mut frame_event: EventWriter<AnimationFrameEvent>,
 ) {
-    for (indices, mut sprite, player_state) in &mut query {
-        let indices = indices[&player_state];
-        // Jump directly to the correct frame when the state has changed
-        sprite.index = indices.0;
+    for (character, animations_handle, mut sprite, player_state, timer, speed) in &mut query {
+        if let Some(clip) = sprite_animations
+            .get(animations_handle)
+            .and_then(|animations| animations.clips.get(player_state))
+        {
+            // Jump directly to the correct frame when the state has changed
+            sprite.index = clip.frames.0;
+
+            // The first frame lasts its own duration
+            if let Some(mut timer) = timer {
+                let speed = speed.map_or(1., |speed| **speed);
+                timer.set_duration(std::time::Duration::from_secs_f32(
+                    clip.frame_duration(sprite.index) / speed,
+                ));
+                timer.reset();
+            }
+
+            send_frame_events(
+                &mut frame_event,
+                character,
+                player_state,
+                clip,
+                sprite.index,
+            );
+        }
+    }
+}
+
+/// The named events of this frame of the clip, if any
+fn send_frame_events(
+    frame_event: &mut EventWriter<AnimationFrameEvent>,
+    character: Entity,
+    state: &CharacterState,
+    clip: &AnimationClip,
+    frame: usize,
+) {
+    if let Some(names) = clip.events.get(&frame) {
+        for name in names {
+            frame_event.send(AnimationFrameEvent {
+                entity: character,
+                state: state.clone(),
+                frame,
+                name: name.clone(),
+            });
+        }
     }
 }
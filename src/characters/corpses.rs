@@ -0,0 +1,123 @@
+//! The dead bodies left behind by each soul shift.
+//!
+//! They stay in the arena,
+//! can be reclaimed by their soul for a short time
+//! and fade out when there is too many of them.
+
+use bevy::prelude::*;
+
+use crate::{
+    characters::aggression::DeadBody,
+    constants::corpses::{CORPSE_FADE_OUT_DURATION, MAX_CORPSES},
+    crowd::CrowdTint,
+    simulation::GameplayApp,
+};
+
+pub struct CorpsesPlugin;
+
+impl Plugin for CorpsesPlugin {
+    #[rustfmt::skip]
+    fn build(&self, app: &mut App) {
+        app .add_gameplay_system(reclaim_window_timer)
+            .add_gameplay_system(reclaimable_hint)
+            .add_gameplay_system(limit_corpses)
+            .add_gameplay_system(fade_out_corpses)
+            ;
+    }
+}
+
+/// The n-th death, used to find the oldest corpses.
+#[derive(Component, Deref, DerefMut)]
+pub struct DeathOrder(pub i32);
+
+/// While this timer is running,
+/// the corpse can be the target of a soul shift.
+///
+/// See `soul_shift::start_soul_shift`
+#[derive(Component, Deref, DerefMut)]
+pub struct Reclaimable(pub Timer);
+
+/// The corpse is disappearing, and will be despawned at the end of the timer.
+#[derive(Component, Deref, DerefMut)]
+pub struct FadingOut(pub Timer);
+
+/// Close the reclaim window when the timer finishes.
+fn reclaim_window_timer(
+    mut commands: Commands,
+    time: Res<Time>,
+
+    mut corpse_query: Query<(Entity, &mut Reclaimable), With<DeadBody>>,
+) {
+    for (corpse, mut reclaim_window) in corpse_query.iter_mut() {
+        reclaim_window.tick(time.delta());
+
+        if reclaim_window.just_finished() {
+            commands.entity(corpse).remove::<Reclaimable>();
+        }
+    }
+}
+
+/// Give the reclaimable corpses a soul-ish tint,
+/// until they are reclaimed or the window is closed.
+fn reclaimable_hint(
+    new_reclaimable_query: Query<Entity, Added<Reclaimable>>,
+    removed_reclaimable: RemovedComponents<Reclaimable>,
+    mut sprite_query: Query<(&mut TextureAtlasSprite, Option<&CrowdTint>), Without<FadingOut>>,
+) {
+    for corpse in new_reclaimable_query.iter() {
+        if let Ok((mut sprite, _)) = sprite_query.get_mut(corpse) {
+            sprite.color = Color::rgb(0.7, 0.85, 1.);
+        }
+    }
+
+    // Back to their own colours
+    for entity in removed_reclaimable.iter() {
+        if let Ok((mut sprite, tint)) = sprite_query.get_mut(entity) {
+            sprite.color = tint.map_or(Color::WHITE, |tint| **tint);
+        }
+    }
+}
+
+/// Start to fade out the oldest corpses when there is more than `MAX_CORPSES`.
+fn limit_corpses(
+    mut commands: Commands,
+    corpse_query: Query<(Entity, &DeathOrder), (With<DeadBody>, Without<FadingOut>)>,
+) {
+    let corpses_count = corpse_query.iter().len();
+
+    if corpses_count > MAX_CORPSES {
+        let mut corpses: Vec<(Entity, i32)> = corpse_query
+            .iter()
+            .map(|(corpse, death_order)| (corpse, **death_order))
+            .collect();
+        corpses.sort_by_key(|(_, death_order)| *death_order);
+
+        for (oldest_corpse, _) in corpses.iter().take(corpses_count - MAX_CORPSES) {
+            commands
+                .entity(*oldest_corpse)
+                .remove::<Reclaimable>()
+                .insert(FadingOut(Timer::from_seconds(
+                    CORPSE_FADE_OUT_DURATION,
+                    TimerMode::Once,
+                )));
+        }
+    }
+}
+
+fn fade_out_corpses(
+    mut commands: Commands,
+    time: Res<Time>,
+
+    mut corpse_query: Query<(Entity, &mut FadingOut, &mut TextureAtlasSprite), With<DeadBody>>,
+) {
+    for (corpse, mut fading_out, mut sprite) in corpse_query.iter_mut() {
+        fading_out.tick(time.delta());
+
+        let alpha = fading_out.percent_left();
+        sprite.color.set_a(alpha);
+
+        if fading_out.just_finished() {
+            commands.entity(corpse).despawn_recursive();
+        }
+    }
+}
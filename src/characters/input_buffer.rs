@@ -0,0 +1,97 @@
+//! Recent inputs of the player, kept for a short time.
+//!
+//! A press during an animation isn't lost:
+//! it waits in the buffer until the animation reaches a frame
+//! where it can be consumed (combo window or recovery frame).
+
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+
+use crate::{
+    characters::{
+        input_map::{Control, Controls, InputMap},
+        player::Player,
+    },
+    constants::character::player::INPUT_BUFFER_DURATION,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlayerAction {
+    AttackPressed,
+    AttackReleased,
+    Dash,
+    Parry,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct BufferedAction {
+    pub action: PlayerAction,
+    /// `Time::elapsed_seconds_f64` when the input happened
+    pub time: f64,
+}
+
+/// Oldest actions first.
+#[derive(Component, Debug, Default)]
+pub struct InputBuffer(pub VecDeque<BufferedAction>);
+
+impl InputBuffer {
+    pub fn push(&mut self, action: PlayerAction, time: f64) {
+        self.0.push_back(BufferedAction { action, time });
+    }
+
+    /// The oldest of these actions
+    pub fn next(&self, actions: &[PlayerAction]) -> Option<BufferedAction> {
+        self.0
+            .iter()
+            .find(|buffered_action| actions.contains(&buffered_action.action))
+            .copied()
+    }
+
+    /// Remove the oldest of these actions
+    pub fn consume(&mut self, actions: &[PlayerAction]) -> Option<BufferedAction> {
+        let index = self
+            .0
+            .iter()
+            .position(|buffered_action| actions.contains(&buffered_action.action))?;
+        self.0.remove(index)
+    }
+
+    /// Forget the actions older than `INPUT_BUFFER_DURATION`
+    pub fn expire(&mut self, now: f64) {
+        while let Some(buffered_action) = self.0.front() {
+            if now - buffered_action.time > INPUT_BUFFER_DURATION {
+                self.0.pop_front();
+            } else {
+                break;
+            }
+        }
+    }
+}
+
+/// Store the actions of the frame, whatever the player is doing.
+pub fn buffer_player_inputs(
+    time: Res<Time>,
+    controls: Controls,
+
+    mut player_query: Query<(&mut InputBuffer, &InputMap), With<Player>>,
+) {
+    let now = time.elapsed_seconds_f64();
+
+    for (mut input_buffer, input_map) in player_query.iter_mut() {
+        input_buffer.expire(now);
+
+        if controls.just_pressed(input_map, Control::Attack) {
+            input_buffer.push(PlayerAction::AttackPressed, now);
+        }
+        if controls.just_released(input_map, Control::Attack) {
+            input_buffer.push(PlayerAction::AttackReleased, now);
+        }
+        if controls.just_pressed(input_map, Control::Dash) {
+            input_buffer.push(PlayerAction::Dash, now);
+        }
+        if controls.just_pressed(input_map, Control::Parry) {
+            input_buffer.push(PlayerAction::Parry, now);
+        }
+    }
+}
@@ -0,0 +1,162 @@
+//! Keys, buttons and gamepad of each player.
+//!
+//! Alone, the player has the whole keyboard and the mouse.
+//! In versus, the keyboard is split in two halves and each player has their own gamepad.
+
+use std::marker::PhantomData;
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Control {
+    Left,
+    Right,
+    Attack,
+    Dash,
+    Parry,
+    /// Leave the body, see `soul_shift::suicide_to_soul_shift`
+    GiveUp,
+}
+
+/// The devices controlling a character.
+///
+/// On a gamepad: the D-pad moves, South attacks, East dashes,
+/// West parries and Select gives up.
+/// The sticks are ignored: they couldn't be replayed.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InputMap {
+    pub left: &'static [KeyCode],
+    pub right: &'static [KeyCode],
+    pub attack: &'static [KeyCode],
+    pub dash: &'static [KeyCode],
+    pub parry: &'static [KeyCode],
+    pub give_up: &'static [KeyCode],
+    /// Left click attacks, right click parries
+    pub mouse: bool,
+    pub gamepad: Option<Gamepad>,
+}
+
+impl InputMap {
+    /// QWERTY and AZERTY
+    pub const SOLO: InputMap = InputMap {
+        left: &[KeyCode::Q, KeyCode::A, KeyCode::Left],
+        right: &[KeyCode::D, KeyCode::Right],
+        attack: &[KeyCode::Return],
+        dash: &[KeyCode::LShift],
+        parry: &[KeyCode::F],
+        give_up: &[KeyCode::E],
+        mouse: true,
+        gamepad: Some(Gamepad { id: 0 }),
+    };
+
+    /// The left half of the keyboard
+    pub const PLAYER_ONE: InputMap = InputMap {
+        left: &[KeyCode::Q, KeyCode::A],
+        right: &[KeyCode::D],
+        attack: &[KeyCode::Space],
+        dash: &[KeyCode::LShift],
+        parry: &[KeyCode::F],
+        give_up: &[KeyCode::E],
+        mouse: false,
+        gamepad: Some(Gamepad { id: 0 }),
+    };
+
+    /// The right half of the keyboard
+    pub const PLAYER_TWO: InputMap = InputMap {
+        left: &[KeyCode::Left],
+        right: &[KeyCode::Right],
+        attack: &[KeyCode::Return],
+        dash: &[KeyCode::RShift],
+        parry: &[KeyCode::RControl],
+        give_up: &[KeyCode::Back],
+        mouse: false,
+        gamepad: Some(Gamepad { id: 1 }),
+    };
+
+    pub fn keys(&self, control: Control) -> &'static [KeyCode] {
+        match control {
+            Control::Left => self.left,
+            Control::Right => self.right,
+            Control::Attack => self.attack,
+            Control::Dash => self.dash,
+            Control::Parry => self.parry,
+            Control::GiveUp => self.give_up,
+        }
+    }
+
+    pub fn mouse_button(&self, control: Control) -> Option<MouseButton> {
+        if !self.mouse {
+            return None;
+        }
+
+        match control {
+            Control::Attack => Some(MouseButton::Left),
+            Control::Parry => Some(MouseButton::Right),
+            _ => None,
+        }
+    }
+
+    pub fn gamepad_button(&self, control: Control) -> Option<GamepadButton> {
+        let button_type = match control {
+            Control::Left => GamepadButtonType::DPadLeft,
+            Control::Right => GamepadButtonType::DPadRight,
+            Control::Attack => GamepadButtonType::South,
+            Control::Dash => GamepadButtonType::East,
+            Control::Parry => GamepadButtonType::West,
+            Control::GiveUp => GamepadButtonType::Select,
+        };
+        self.gamepad
+            .map(|gamepad| GamepadButton::new(gamepad, button_type))
+    }
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        InputMap::SOLO
+    }
+}
+
+/// The state of the controls, through an `InputMap`.
+#[derive(SystemParam)]
+pub struct Controls<'w, 's> {
+    keyboard_input: Res<'w, Input<KeyCode>>,
+    buttons: Res<'w, Input<MouseButton>>,
+    gamepad_buttons: Res<'w, Input<GamepadButton>>,
+    #[system_param(ignore)]
+    _marker: PhantomData<&'s ()>,
+}
+
+impl<'w, 's> Controls<'w, 's> {
+    pub fn pressed(&self, input_map: &InputMap, control: Control) -> bool {
+        self.keyboard_input
+            .any_pressed(input_map.keys(control).iter().copied())
+            || input_map
+                .mouse_button(control)
+                .is_some_and(|button| self.buttons.pressed(button))
+            || input_map
+                .gamepad_button(control)
+                .is_some_and(|button| self.gamepad_buttons.pressed(button))
+    }
+
+    pub fn just_pressed(&self, input_map: &InputMap, control: Control) -> bool {
+        self.keyboard_input
+            .any_just_pressed(input_map.keys(control).iter().copied())
+            || input_map
+                .mouse_button(control)
+                .is_some_and(|button| self.buttons.just_pressed(button))
+            || input_map
+                .gamepad_button(control)
+                .is_some_and(|button| self.gamepad_buttons.just_pressed(button))
+    }
+
+    pub fn just_released(&self, input_map: &InputMap, control: Control) -> bool {
+        self.keyboard_input
+            .any_just_released(input_map.keys(control).iter().copied())
+            || input_map
+                .mouse_button(control)
+                .is_some_and(|button| self.buttons.just_released(button))
+            || input_map
+                .gamepad_button(control)
+                .is_some_and(|button| self.gamepad_buttons.just_released(button))
+    }
+}
@@ -1,14 +1,28 @@
 pub mod aggression;
 pub mod animations;
+pub mod corpses;
+pub mod input_buffer;
+pub mod input_map;
 pub mod movement;
+pub mod moveset;
 pub mod npcs;
 pub mod player;
+pub mod stamina;
+pub mod state_machine;
 
 use bevy::prelude::*;
 
+use crate::{game_feel::not_in_hit_stop, simulation::GameplayApp};
+
 use self::{
-    aggression::AggressionPlugin, animations::animate_character,
-    animations::jump_frame_player_state, player::PlayerPlugin, npcs::NPCsPlugin,
+    aggression::AggressionPlugin,
+    animations::{
+        animate_character, jump_frame_player_state, AnimationFrameEvent, SpriteAnimations,
+        SpriteAnimationsLoader,
+    },
+    corpses::CorpsesPlugin, player::PlayerPlugin, npcs::NPCsPlugin,
+    stamina::StaminaPlugin,
+    state_machine::{change_state, ChangeStateEvent},
 };
 
 pub struct CharacterPlugin;
@@ -20,34 +34,16 @@ impl Plugin for CharacterPlugin {
             .add_plugin(NPCsPlugin)
             .add_plugin(PlayerPlugin)
             .add_plugin(AggressionPlugin)
+            .add_plugin(CorpsesPlugin)
+            .add_plugin(StaminaPlugin)
             // -- Animation --
-            .add_system(animate_character)
-            .add_system(jump_frame_player_state)
-            .add_system(move_dead_bodies)
+            .add_asset::<SpriteAnimations>()
+            .init_asset_loader::<SpriteAnimationsLoader>()
+            .add_gameplay_event::<AnimationFrameEvent>()
+            .add_gameplay_system(animate_character.with_run_criteria(not_in_hit_stop))
+            .add_gameplay_event::<ChangeStateEvent>()
+            .add_gameplay_system(change_state.label("Change State").after(animate_character))
+            .add_gameplay_system(jump_frame_player_state.after("Change State"))
             ;
     }
 }
-
-#[derive(Component)]
-pub struct Invulnerable;
-
-#[derive(Component)]
-pub struct DeadBody;
-
-fn move_dead_bodies(
-    mut query: Query<&mut Transform, With<DeadBody>>,
-    keyboard_input: Res<Input<KeyCode>>,
-    time: Res<Time>,
-) {
-    for mut transform in query.iter_mut() {
-        let right = keyboard_input.pressed(KeyCode::D) || keyboard_input.pressed(KeyCode::Right);
-        let left = keyboard_input.pressed(KeyCode::Q)
-            || keyboard_input.pressed(KeyCode::A)
-            || keyboard_input.pressed(KeyCode::Left);
-
-        let dir = right as i8 - left as i8;
-        const SPEED: f32 = 25.0;
-
-        transform.translation.x += -dir as f32 * SPEED * time.delta_seconds();
-    }
-}
@@ -2,7 +2,7 @@ use bevy::prelude::*;
 use bevy_rapier2d::prelude::Velocity;
 // use bevy_retrograde::prelude::Velocity;
 
-use crate::TILE_SIZE;
+use crate::constants::TILE_SIZE;
 
 // find the right place to put this component (indicator)
 #[derive(Component)]
@@ -17,6 +17,10 @@ impl Default for Speed {
     }
 }
 
+/// A quick burst in the facing direction, until the end of the timer
+#[derive(Component, Deref, DerefMut)]
+pub struct Dashing(pub Timer);
+
 #[derive(Bundle)]
 pub struct MovementBundle {
     pub speed: Speed,
@@ -0,0 +1,237 @@
+//! Movesets
+//!
+//! What a controlled body can do: its animations, its hurtbox, its attack hitboxes and its stats.
+//!
+//! The player's systems (see `characters::player`) drive any body the same way:
+//! the whip of a spectator or the smash of a possessed boss only differ by their `Moveset`.
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    characters::{
+        aggression::{
+            AttackContacts, AttackHitbox, AttackHitboxKind, AttackSensor, AttackTargets,
+            DamagePolicy, HitReaction, Poise,
+        },
+        movement::CharacterHitbox,
+    },
+    collisions::{attack_hitbox_groups, Team},
+    config::GameplayConfig,
+    constants::{
+        character::{boss::*, player::*},
+        TILE_SIZE,
+    },
+    game_feel::ImpactFeel,
+};
+
+/// The moves of a body, which stay with it whoever controls it.
+///
+/// A body without one has the `Whip`.
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Moveset {
+    /// The spectators: light swings of the whip
+    #[default]
+    Whip,
+    /// Smash and Fallen Angel
+    Boss,
+}
+
+/// An attack hitbox of a moveset,
+/// activated by the `hitboxes` of the animation clips.
+struct AttackShape {
+    kind: AttackHitboxKind,
+    /// Of its `AttackSensor`, facing right
+    position: (f32, f32, f32),
+    /// Half extents of the cuboid
+    size: (f32, f32),
+    name: &'static str,
+}
+
+const WHIP_ATTACKS: &[AttackShape] = &[
+    AttackShape {
+        kind: AttackHitboxKind::BottomWhip,
+        position: BOTTOM_WHIP_POS,
+        size: PLAYER_ATTACK_HITBOX_BOTTOM,
+        name: "Bottom Whip",
+    },
+    AttackShape {
+        kind: AttackHitboxKind::FrontBall,
+        position: FRONT_WHIP_POS,
+        size: PLAYER_ATTACK_HITBOX_FRONT,
+        name: "Front Ball",
+    },
+];
+
+const BOSS_ATTACKS: &[AttackShape] = &[
+    AttackShape {
+        kind: AttackHitboxKind::Smash,
+        position: FRONT_SMASH_POS_TOP,
+        size: BOSS_ATTACK_HITBOX_SMASH_TOP,
+        name: "Smash Top",
+    },
+    AttackShape {
+        kind: AttackHitboxKind::Smash,
+        position: FRONT_SMASH_POS_BOTTOM,
+        size: BOSS_ATTACK_HITBOX_SMASH_BOTTOM,
+        name: "Smash Bot",
+    },
+    AttackShape {
+        kind: AttackHitboxKind::FallenAngel,
+        position: FALLEN_ANGEL_POS,
+        size: BOSS_ATTACK_HITBOX_FALLEN_ANGEL,
+        name: "Fallen Angel",
+    },
+];
+
+impl Moveset {
+    pub fn animations(self) -> &'static str {
+        match self {
+            Moveset::Whip => PLAYER_ANIMATIONS,
+            Moveset::Boss => BOSS_ANIMATIONS,
+        }
+    }
+
+    pub fn atlas(self) -> &'static str {
+        match self {
+            Moveset::Whip => PLAYER_ANIMATIONS_ATLAS,
+            Moveset::Boss => BOSS_ANIMATIONS_ATLAS,
+        }
+    }
+
+    pub fn hp(self, config: &GameplayConfig) -> i32 {
+        match self {
+            Moveset::Whip => config.player.hp,
+            Moveset::Boss => config.boss.hp,
+        }
+    }
+
+    /// In pixels per second
+    pub fn speed(self, config: &GameplayConfig) -> f32 {
+        match self {
+            Moveset::Whip => config.player.speed * TILE_SIZE,
+            Moveset::Boss => config.boss.speed * TILE_SIZE,
+        }
+    }
+
+    pub fn hit_invulnerability(self, config: &GameplayConfig) -> f32 {
+        match self {
+            Moveset::Whip => config.player.hit_invulnerability,
+            Moveset::Boss => config.boss.hit_invulnerability,
+        }
+    }
+
+    pub fn damage_policy(self, config: &GameplayConfig) -> DamagePolicy {
+        match self {
+            Moveset::Whip => DamagePolicy {
+                invulnerability: self.hit_invulnerability(config),
+                hit_reaction: HitReaction::Flinch,
+                armor: PLAYER_ARMOR,
+                damage_multiplier: PLAYER_DAMAGE_MULTIPLIER,
+            },
+            Moveset::Boss => DamagePolicy {
+                invulnerability: self.hit_invulnerability(config),
+                hit_reaction: HitReaction::Poise,
+                armor: BOSS_ARMOR,
+                damage_multiplier: BOSS_DAMAGE_MULTIPLIER,
+            },
+        }
+    }
+
+    /// Only the boss tanks the hits until stunned
+    pub fn poise(self) -> Option<Poise> {
+        match self {
+            Moveset::Whip => None,
+            Moveset::Boss => Some(Poise::new(
+                BOSS_POISE,
+                BOSS_POISE_REGEN,
+                BOSS_POISE_REGEN_DELAY,
+            )),
+        }
+    }
+
+    fn attacks(self) -> &'static [AttackShape] {
+        match self {
+            Moveset::Whip => WHIP_ATTACKS,
+            Moveset::Boss => BOSS_ATTACKS,
+        }
+    }
+
+    /// Where the body is hurt by the opponents of its team
+    pub fn hurtbox(
+        self,
+        team: Team,
+    ) -> (
+        Collider,
+        Transform,
+        CharacterHitbox,
+        CollisionGroups,
+        Sensor,
+        Name,
+    ) {
+        let (size, offset, name) = match self {
+            Moveset::Whip => (PLAYER_HITBOX_SIZE, PLAYER_HITBOX_OFFSET_Y, "Player Hitbox"),
+            Moveset::Boss => (BOSS_HITBOX_SIZE, BOSS_HITBOX_OFFSET_Y, "Boss Hitbox"),
+        };
+
+        (
+            Collider::ball(size),
+            Transform::from_translation(offset.into()),
+            CharacterHitbox,
+            team.hurtbox_groups(),
+            Sensor,
+            Name::new(name),
+        )
+    }
+
+    /// Spawns the attack hitboxes in the body, facing right (1.) or left (-1.).
+    ///
+    /// They only meet the opponents of the team,
+    /// and only on certain frames: see the `hitboxes` of the animation clips.
+    pub fn spawn_attack_hitboxes(
+        self,
+        parent: &mut ChildBuilder,
+        team: Team,
+        facing: f32,
+        config: &GameplayConfig,
+    ) {
+        for attack in self.attacks() {
+            parent
+                .spawn((
+                    SpatialBundle {
+                        transform: Transform::from_xyz(
+                            attack.position.0 * facing,
+                            attack.position.1,
+                            attack.position.2,
+                        ),
+                        ..default()
+                    },
+                    AttackSensor,
+                    RigidBody::Dynamic,
+                    Name::new(format!("Parent - {}", attack.name)),
+                ))
+                .with_children(|parent| {
+                    let mut hitbox = parent.spawn((
+                        Collider::cuboid(attack.size.0, attack.size.1),
+                        TransformBundle::default(),
+                        AttackHitbox(config.attack_damage(attack.kind)),
+                        attack.kind,
+                        attack_hitbox_groups(team.attack_group()),
+                        AttackTargets(team.attack_targets()),
+                        AttackContacts::default(),
+                        Sensor,
+                        Name::new(format!("Attack Hitbox: Sensor - {}", attack.name)),
+                    ));
+
+                    // The smash is felt, whoever throws it
+                    if attack.kind == AttackHitboxKind::Smash {
+                        hitbox.insert(ImpactFeel {
+                            hit_stop: BOSS_SMASH_HIT_STOP,
+                            shake_per_damage: BOSS_SMASH_SHAKE_PER_DAMAGE,
+                            ..default()
+                        });
+                    }
+                });
+        }
+    }
+}
@@ -5,17 +5,18 @@ use bevy_rapier2d::prelude::*;
 
 use crate::{
     characters::{
-        aggression::{AttackCooldown, AttackHitbox, AttackSensor, Hp},
+        aggression::AttackCooldown,
         // Invulnerable,
         animations::CharacterState,
         movement::CharacterHitbox,
-        player::PlayerHitbox,
+        player::{Player, PlayerHitbox},
+        state_machine::ChangeStateEvent,
     },
-    // collisions::CollisionEventExt,
-    constants::character::boss::BOSS_SMASH_COOLDOWN,
+    collisions::CollisionEventExt,
+    config::GameplayConfig,
 };
 
-use super::{Boss, BossAttackFalleAngel, BossAttackSmash};
+use super::{Boss, BossBehaviour};
 
 // pub struct AggressionBossPlugin;
 
@@ -48,79 +49,99 @@ pub struct BossAttackEvent {
     attacker_entity: Entity,
 }
 
-#[derive(Component)]
-pub struct BossSensor;
+/// The player hurtboxes inside the range of the boss.
+///
+/// Kept up to date by `boss_range_detection`
+#[derive(Component, Default)]
+pub struct BossSensor {
+    pub in_range: Vec<Entity>,
+}
 
-/// DEBUG: TEMPORARY
+/// Follow the player hurtboxes entering and leaving the sensor.
 ///
-/// The Boss' hp won't be displayed.
-/// The current phase will indicate, as well as the clouds ?
-pub fn display_boss_hp(
-    bleeding_boss_query: Query<&Hp, (With<Boss>, Or<(Added<Hp>, Changed<Hp>)>)>,
+/// The collision groups of the sensor only let the player's hurtbox in.
+pub fn boss_range_detection(
+    mut collision_events: EventReader<CollisionEvent>,
+
+    mut boss_sensor_query: Query<&mut BossSensor>,
 ) {
-    if let Ok(boss_hp) = bleeding_boss_query.get_single() {
-        println!("boss's hp: {}/{}", boss_hp.current, boss_hp.max);
+    for collision_event in collision_events.iter() {
+        // The other one can only be a player hurtbox
+        let (sensor, player_sensor) = match collision_event.entities() {
+            (e1, e2) if boss_sensor_query.contains(e1) => (e1, e2),
+            (e1, e2) if boss_sensor_query.contains(e2) => (e2, e1),
+            _ => continue,
+        };
+
+        if let Ok(mut boss_sensor) = boss_sensor_query.get_mut(sensor) {
+            if collision_event.is_started() {
+                boss_sensor.in_range.push(player_sensor);
+            } else {
+                boss_sensor
+                    .in_range
+                    .retain(|entity| *entity != player_sensor);
+            }
+        }
     }
 }
 
 /// When the player enters the sensor
 /// The boss start to attack them
 ///
-/// Send a Event to launch a attack when a entity enters the sensor
+/// Send a Event to launch a attack when a entity is in the sensor
 /// and insert a timer to limit the number of attack while still in the sensor.
 ///
-/// Remove this timer (and behavior?) when leaving the sensor
-///
 /// ***IMPORTANT***:
-/// - when dying/tp the exit trigger of the sensor will not trigger
-/// Has to verify that the entity is nearby
-/// OR
-/// when tp/dying->soul shift the player become Invulnerable
-/// - If no player vulnerable, then remove the behavior
+/// - when dying/tp the exit trigger of the sensor may not trigger:
+/// only the hurtboxes still owned by a player count
 ///
 /// # Note
 ///
 /// The boss should still attack while the player is invulnerable;
-///
-/// And we could just deactivate the player hitbox if Invulnerable by removing `ActiveEvents`
-///
-/// ^^^----- I don't know if that could just work: (if not) for more depts: [Collision groups and solver groups](https://rapier.rs/docs/user_guides/bevy_plugin/colliders/#collision-groups-and-solver-groups)
 pub fn boss_close_detection(
     mut commands: Commands,
 
-    // mut collision_events: EventReader<CollisionEvent>,
-    rapier_context: Res<RapierContext>,
-
     boss_attack_sensor_query: Query<
-        (Entity, &Parent),
-        (With<Sensor>, With<BossSensor>, Without<AttackCooldown>),
+        (Entity, &BossSensor, &Parent),
+        (With<Sensor>, Without<AttackCooldown>),
     >,
-    player_sensor_query: Query<(Entity, &Parent), (With<PlayerHitbox>, With<CharacterHitbox>)>,
+    player_sensor_query: Query<Entity, (With<PlayerHitbox>, With<CharacterHitbox>)>,
+    boss_query: Query<(&CharacterState, &BossBehaviour), With<Boss>>,
+    config: Res<GameplayConfig>,
 
     mut boss_attack_event: EventWriter<BossAttackEvent>,
 ) {
     // Phase 1 - Sensor
-    if let Ok((attack_sensor, boss)) = boss_attack_sensor_query.get_single() {
-        if let Ok((player_sensor, _player)) = player_sensor_query.get_single() {
-            // Phase 3 - Player TP proof
-            if rapier_context.intersection_pair(attack_sensor, player_sensor) == Some(true) {
-                // IDEA: MUST-HAVE - Disable turn/movement when the boss attack (avoid spinning attack when passing behind the boss)
-                // ^^^^^------ With Dash/Death TP for example
-
-                boss_attack_event.send(BossAttackEvent {
-                    attacker_entity: **boss,
-                });
-
-                // Phase 2 - Timer
-                commands
-                    .entity(attack_sensor) // **boss
-                    .insert(AttackCooldown(Timer::from_seconds(
-                        BOSS_SMASH_COOLDOWN,
-                        TimerMode::Once,
-                    )));
-            }
+    if let Ok((attack_sensor, boss_sensor, boss)) = boss_attack_sensor_query.get_single() {
+        match boss_query.get(**boss) {
+            // The punish window
+            Ok((CharacterState::Stunned, _)) => return,
+            Ok((_, BossBehaviour::Aggressive)) => {}
+            // Scripted, see `boss_scripted_attacks`
+            Ok(_) | Err(_) => return,
+        }
+
+        // Phase 3 - Player TP proof
+        if boss_sensor
+            .in_range
+            .iter()
+            .any(|player_sensor| player_sensor_query.contains(*player_sensor))
+        {
+            // IDEA: MUST-HAVE - Disable turn/movement when the boss attack (avoid spinning attack when passing behind the boss)
+            // ^^^^^------ With Dash/Death TP for example
+
+            boss_attack_event.send(BossAttackEvent {
+                attacker_entity: **boss,
+            });
+
+            // Phase 2 - Timer
+            commands
+                .entity(attack_sensor) // **boss
+                .insert(AttackCooldown(Timer::from_seconds(
+                    config.boss.smash_cooldown,
+                    TimerMode::Once,
+                )));
         }
-        // else { info!("No playerHitbox") }
     }
 }
 
@@ -128,85 +149,47 @@ pub fn boss_attack_event_handler(
     mut boss_attack_event: EventReader<BossAttackEvent>,
     // If needed to check the Player Invulnerability state:
     // player_query: Query<Entity, (With<Player>, Without<Invulnerable>)>,
-    mut attacker_query: Query<&mut CharacterState>,
+    mut change_state_event: EventWriter<ChangeStateEvent>,
 ) {
     for BossAttackEvent { attacker_entity } in boss_attack_event.iter() {
-        match attacker_query.get_mut(*attacker_entity) {
-            // DEBUG: (in the start of the game) / Every time a entity spawns, log the name + current identifier
-            Err(e) => warn!(
-                "This entity: {:?} Cannot animate: {:?}",
-                *attacker_entity, e
-            ),
-            Ok(mut state) => {
-                *state = CharacterState::Attack;
-            }
-        }
+        change_state_event.send(ChangeStateEvent {
+            entity: *attacker_entity,
+            state: CharacterState::Attack,
+        });
     }
 }
 
-/// Activate when the character is on animation phase Attack,
-/// Deactivate else.
-pub fn boss_attack_hitbox_activation(
-    mut commands: Commands,
-
-    boss_query: Query<(&CharacterState, &Children, &Name), (Changed<CharacterState>, With<Boss>)>,
-    parent_hitbox_position_query: Query<(Entity, &Children), With<AttackSensor>>,
-
-    // All Kind of Boss Attack
-    smash_hitbox_query: Query<Entity, (With<AttackHitbox>, With<BossAttackSmash>, With<Sensor>)>,
-    fallen_angel_hitbox_query: Query<
-        Entity,
-        (With<AttackHitbox>, With<BossAttackFalleAngel>, With<Sensor>),
-    >,
+/// The attacks of a boss which isn't `BossBehaviour::Aggressive`.
+///
+/// The state machine refuses them until the current clip can be interrupted.
+pub fn boss_scripted_attacks(
+    boss_query: Query<(Entity, &CharacterState, &BossBehaviour), With<Boss>>,
+    player_query: Query<&CharacterState, (With<Player>, Changed<CharacterState>)>,
+    mut change_state_event: EventWriter<ChangeStateEvent>,
 ) {
-    for (character_state, children, _name) in boss_query.iter() {
-        // info!("DEBUG: {} Changed {:?}", name, *character_state);
-        for child in children.iter() {
-            match parent_hitbox_position_query.get(*child) {
-                Err(_) => continue,
-                // The parent Hitbox contains the modifiable transform
-                // for all their hitbox children
-                Ok((_parent_hitbox, hitbox_children)) => {
-                    for hitbox_child in hitbox_children.iter() {
-                        // OPTIMIZE: Hitbox Activation - This statement will be called a bit too much
-                        // vv-- to just see uncomment the two DEBUG info below --vv
-
-                        // Just activate the good group of AttackHitbox (Smash or FallenAngel)
-                        match (
-                            smash_hitbox_query.get(*hitbox_child),
-                            fallen_angel_hitbox_query.get(*hitbox_child),
-                        ) {
-                            (Ok(smash), Err(_)) => {
-                                if *character_state == CharacterState::Attack {
-                                    // info!("DEBUG: Smash Active Inserted on {}", _name);
-                                    commands
-                                        .entity(smash)
-                                        .insert(ActiveEvents::COLLISION_EVENTS);
-                                } else {
-                                    // info!("DEBUG: Smash Active Removed on {}", _name);
-                                    commands.entity(smash).remove::<ActiveEvents>();
-                                }
-                            }
-                            (Err(_), Ok(fallen_angel)) => {
-                                if *character_state == CharacterState::SecondAttack {
-                                    // info!("DEBUG: Fallen Angel Active Inserted on {}", _name);
-                                    commands
-                                        .entity(fallen_angel)
-                                        .insert(ActiveEvents::COLLISION_EVENTS);
-                                } else {
-                                    // info!("DEBUG: Fallen Angel Active Removed on {}", _name);
-                                    commands.entity(fallen_angel).remove::<ActiveEvents>();
-                                }
-                            }
-                            (Err(_), Err(_)) => warn!("Non Indexed Attack (Smash or Fallen Angel)"),
-                            (Ok(_), Ok(_)) => {
-                                warn!("Attack indexed twice (Smash and Fallen Angel)")
-                            }
-                        }
-                        // match attack_hitbox_query.get(*child)
-                    }
-                }
+    for (boss, boss_state, behaviour) in &boss_query {
+        let attack = match behaviour {
+            BossBehaviour::Aggressive | BossBehaviour::Idle => None,
+            BossBehaviour::Loop(attack) => {
+                (*boss_state == CharacterState::Idle).then(|| attack.clone())
             }
+            BossBehaviour::Mirror => {
+                player_query
+                    .iter()
+                    .find_map(|player_state| match player_state {
+                        CharacterState::Attack
+                        | CharacterState::SecondAttack
+                        | CharacterState::ChargedAttack => Some(player_state.clone()),
+                        _ => None,
+                    })
+            }
+        };
+
+        if let Some(state) = attack {
+            change_state_event.send(ChangeStateEvent {
+                entity: boss,
+                state,
+            });
         }
     }
 }
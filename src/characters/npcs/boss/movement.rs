@@ -8,16 +8,26 @@ use crate::{
 
 use super::Boss;
 
+/// The boss always faces the player (the closest one, in versus).
 pub fn stare_player(
     mut boss_query: Query<(Entity, &mut TextureAtlasSprite, &Transform), With<Boss>>,
     player_query: Query<&Transform, (With<Player>, Without<CrowdMember>)>,
     mut flip_direction_event: EventWriter<FlipAttackSensorEvent>,
 ) {
-    let (boss, mut boss_sprite, boss_transform) = boss_query.single_mut();
-    let player_transform = player_query.single();
+    for (boss, mut boss_sprite, boss_transform) in boss_query.iter_mut() {
+        let boss_x = boss_transform.translation.x;
+        let player_x = match player_query
+            .iter()
+            .map(|player_transform| player_transform.translation.x)
+            .min_by(|a, b| (a - boss_x).abs().total_cmp(&(b - boss_x).abs()))
+        {
+            Some(player_x) => player_x,
+            None => continue,
+        };
 
-    if boss_sprite.flip_x != (boss_transform.translation.x > player_transform.translation.x) {
-        flip_direction_event.send(FlipAttackSensorEvent(boss));
+        if boss_sprite.flip_x != (boss_x > player_x) {
+            flip_direction_event.send(FlipAttackSensorEvent(boss));
+        }
+        boss_sprite.flip_x = boss_x > player_x;
     }
-    boss_sprite.flip_x = boss_transform.translation.x > player_transform.translation.x;
 }
@@ -1,18 +1,34 @@
-use bevy::{prelude::*, utils::HashMap};
+use std::time::Duration;
+
+use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
 
 use crate::{
     camera::camera_follow,
     characters::{
         aggression::{
-            AttackCharge, AttackHitbox, AttackSensor, DeadBody, FlipAttackSensorEvent, Hp,
-            Invulnerable,
+            AttackCharge, Combo, ComboPenalty, DeadBody, FlipAttackSensorEvent, Hp, Invulnerable,
+            Parrying,
+        },
+        animations::{
+            animate_character, AnimationTimer, CharacterState, PlaybackSpeed, SpriteAnimations,
         },
-        animations::{AnimationIndices, AnimationTimer, CharacterState},
-        movement::{CharacterHitbox, MovementBundle, Speed},
+        corpses::{DeathOrder, Reclaimable},
+        input_buffer::{buffer_player_inputs, InputBuffer, PlayerAction},
+        input_map::{Control, Controls, InputMap},
+        movement::{CharacterHitbox, Dashing, MovementBundle, Speed},
+        moveset::Moveset,
+        stamina::Stamina,
+        state_machine::ChangeStateEvent,
+    },
+    collisions::{Team, CORPSE_GROUP},
+    config::GameplayConfig,
+    constants::{
+        character::{player::*, CHAR_POSITION, FRAME_TIME},
+        corpses::CORPSE_RECLAIM_WINDOW,
     },
-    constants::character::{player::*, CHAR_POSITION, FRAME_TIME},
     crowd::CrowdMember,
+    simulation::GameplayApp,
     soul_shift::{start_soul_shift, SoulShifting},
 };
 
@@ -21,20 +37,26 @@ pub struct PlayerPlugin;
 impl Plugin for PlayerPlugin {
     #[rustfmt::skip]
     fn build(&self, app: &mut App) {
-        app .add_event::<CreatePlayerEvent>()
-            .add_event::<PlayerDeathEvent>()
+        app .add_gameplay_event::<CreatePlayerEvent>()
+            .add_gameplay_event::<PlayerDeathEvent>()
             .insert_resource(PossesionCount(1))
             .add_startup_system(spawn_first_player)
-            .add_system(create_player.label("New Beginning").after(start_soul_shift))
+            .add_gameplay_system(create_player.label("New Beginning").after(start_soul_shift))
             // -- Camera --
-            .add_system(camera_follow.after("New Beginning"))
+            // Every frame, after the ticks
+            .add_system(camera_follow)
             // -- Aggression --
-            .add_system(player_attack)
-            .add_system(display_player_hp)
-            .add_system(player_death_event.label("Player Death").before("New Beginning"))
-            .add_system(clean_up_dead_bodies.after("Player Death"))
+            .add_gameplay_system(buffer_player_inputs.label("Buffer Inputs"))
+            .add_gameplay_system(player_attack.after("Buffer Inputs").before("Change State"))
+            .add_gameplay_system(player_combo.after(animate_character).before("Change State"))
+            .add_gameplay_system(combo_penalty_timer)
+            .add_gameplay_system(player_dash.after("Buffer Inputs"))
+            .add_gameplay_system(player_parry.after("Buffer Inputs"))
+            .add_gameplay_system(dash_timer)
+            .add_gameplay_system(player_death_event.label("Player Death").before("New Beginning"))
+            .add_gameplay_system(clean_up_dead_bodies.after("Player Death"))
             // -- Movement --
-            .add_system(player_movement)
+            .add_gameplay_system(player_movement.before("Change State"))
             ;
     }
 }
@@ -48,7 +70,7 @@ pub struct PossesionCount(pub i32);
 #[derive(Component)]
 pub struct PlayerHitbox;
 
-#[derive(Debug, Deref, DerefMut)]
+#[derive(Debug, Clone, Deref, DerefMut)]
 pub struct CreatePlayerEvent(pub Entity);
 
 // #[derive()]
@@ -61,50 +83,308 @@ pub struct CreatePlayerEvent(pub Entity);
 ///   - characters::player::player_death_event
 ///     - Death Animation
 ///     - Soul Shift Event
+#[derive(Debug, Clone)]
 pub struct PlayerDeathEvent(pub Entity);
 
+const ATTACK_ACTIONS: &[PlayerAction] =
+    &[PlayerAction::AttackPressed, PlayerAction::AttackReleased];
+
 /// # Note
 ///
 /// TODO: Make the charge much more valuable than the spamming
 /// ^^^^^---- see characters::aggression::damage_hit Note
 fn player_attack(
-    keyboard_input: Res<Input<KeyCode>>,
-    buttons: Res<Input<MouseButton>>,
+    time: Res<Time>,
+    sprite_animations: Res<Assets<SpriteAnimations>>,
     mut player_query: Query<
         (
             Entity,
-            &mut CharacterState,
+            &mut InputBuffer,
             &mut Velocity,
             &mut AttackCharge,
+            &mut Combo,
+            &mut Stamina,
+            &CharacterState,
+            &TextureAtlasSprite,
+            &Handle<SpriteAnimations>,
+        ),
+        (With<Player>, Without<SoulShifting>, Without<ComboPenalty>),
+    >,
+    mut change_state_event: EventWriter<ChangeStateEvent>,
+) {
+    let now = time.elapsed_seconds_f64();
+
+    for (
+        player,
+        mut input_buffer,
+        mut rb_vel,
+        mut attack_charge,
+        mut combo,
+        mut stamina,
+        state,
+        sprite,
+        animations_handle,
+    ) in player_query.iter_mut()
+    {
+        let animations = sprite_animations.get(animations_handle);
+        let in_combo_window = animations
+            .and_then(|animations| animations.clips.get(state))
+            .and_then(|clip| clip.chain.as_ref())
+            .is_some_and(|chain| chain.is_open(sprite.index));
+        let locked = animations.is_some_and(|animations| animations.is_locked(state, sprite.index));
+
+        // In order: a release is never consumed before its press
+        while let Some(buffered_action) = input_buffer.next(ATTACK_ACTIONS) {
+            match buffered_action.action {
+                PlayerAction::AttackPressed => {
+                    if in_combo_window {
+                        // see player_combo
+                        combo.chained = true;
+                    } else if !locked && !stamina.is_empty() {
+                        attack_charge.charging = true;
+                        attack_charge.timer.reset();
+                        // Held since the press, not since its consumption
+                        attack_charge
+                            .timer
+                            .tick(Duration::from_secs_f64(now - buffered_action.time));
+                        change_state_event.send(ChangeStateEvent {
+                            entity: player,
+                            state: CharacterState::TransitionToCharge,
+                        });
+                    } else {
+                        // Wait for the cancel window or some stamina (or expire)
+                        break;
+                    }
+                }
+                // Otherwise, the press was a combo chain
+                PlayerAction::AttackReleased => {
+                    if attack_charge.charging {
+                        let state = if attack_charge.timer.finished() {
+                            stamina.spend(STAMINA_CHARGED_ATTACK_COST);
+                            CharacterState::ChargedAttack
+                        } else {
+                            stamina.spend(STAMINA_ATTACK_COST);
+                            CharacterState::Attack
+                        };
+                        change_state_event.send(ChangeStateEvent {
+                            entity: player,
+                            state,
+                        });
+
+                        rb_vel.linvel = Vect::ZERO;
+                        attack_charge.charging = false;
+                        attack_charge.timer.reset();
+                    }
+                }
+                PlayerAction::Dash | PlayerAction::Parry => {}
+            }
+
+            input_buffer.consume(ATTACK_ACTIONS);
+        }
+    }
+}
+
+/// Chain the next swing once the current one reaches its recovery frame.
+///
+/// A combo which ends without being chained is punished by a `ComboPenalty`.
+fn player_combo(
+    mut commands: Commands,
+
+    sprite_animations: Res<Assets<SpriteAnimations>>,
+    mut player_query: Query<
+        (
+            Entity,
+            &mut Combo,
+            &mut Stamina,
+            &mut Velocity,
+            &CharacterState,
+            &TextureAtlasSprite,
+            &Handle<SpriteAnimations>,
         ),
         With<Player>,
     >,
+    mut change_state_event: EventWriter<ChangeStateEvent>,
 ) {
-    if let Ok((_player, mut state, mut rb_vel, mut attack_charge)) = player_query.get_single_mut() {
-        if keyboard_input.just_pressed(KeyCode::Return) || buttons.just_pressed(MouseButton::Left) {
-            attack_charge.charging = true;
-            attack_charge.timer.reset();
-            *state = CharacterState::TransitionToCharge;
-        } else if keyboard_input.just_released(KeyCode::Return)
-            || buttons.just_released(MouseButton::Left)
-        {
-            *state = if attack_charge.timer.finished() {
-                CharacterState::ChargedAttack
-            } else {
-                CharacterState::Attack
-            };
+    for (player, mut combo, mut stamina, mut rb_vel, state, sprite, animations_handle) in
+        player_query.iter_mut()
+    {
+        match state {
+            CharacterState::Attack
+            | CharacterState::SecondAttack
+            | CharacterState::ChargedAttack => {
+                let clip = match sprite_animations
+                    .get(animations_handle)
+                    .and_then(|animations| animations.clips.get(state))
+                {
+                    None => continue,
+                    Some(clip) => clip,
+                };
+                combo.open = clip.chain.is_some();
+
+                if let Some(chain) = &clip.chain {
+                    if combo.chained && clip.is_recovered(sprite.index) {
+                        combo.chained = false;
+
+                        // Out of breath: the combo is dropped
+                        if !stamina.is_empty() {
+                            stamina.spend(STAMINA_ATTACK_COST);
+                            combo.count += 1;
+                            change_state_event.send(ChangeStateEvent {
+                                entity: player,
+                                state: chain.next.clone(),
+                            });
+                        }
+                    }
+                }
+            }
+            // Interrupted
+            CharacterState::Hit | CharacterState::Dead => {
+                if combo.count > 0 || combo.chained || combo.open {
+                    *combo = Combo::default();
+                }
+            }
+            // Dropped, or ended by its last swing
+            _ => {
+                if combo.count > 0 && combo.open {
+                    rb_vel.linvel = Vect::ZERO;
+                    commands
+                        .entity(player)
+                        .insert(ComboPenalty(Timer::from_seconds(
+                            COMBO_DROP_PENALTY,
+                            TimerMode::Once,
+                        )));
+                }
+                if combo.count > 0 || combo.chained || combo.open {
+                    *combo = Combo::default();
+                }
+            }
+        }
+    }
+}
+
+/// A quick burst in the facing direction, once the animation allows it.
+fn player_dash(
+    mut commands: Commands,
+
+    sprite_animations: Res<Assets<SpriteAnimations>>,
+    mut player_query: Query<
+        (
+            Entity,
+            &mut InputBuffer,
+            &mut Stamina,
+            &mut Velocity,
+            &Speed,
+            &TextureAtlasSprite,
+            &CharacterState,
+            &Handle<SpriteAnimations>,
+        ),
+        (
+            With<Player>,
+            Without<SoulShifting>,
+            Without<ComboPenalty>,
+            Without<Dashing>,
+        ),
+    >,
+) {
+    for (
+        player,
+        mut input_buffer,
+        mut stamina,
+        mut rb_vel,
+        speed,
+        sprite,
+        state,
+        animations_handle,
+    ) in player_query.iter_mut()
+    {
+        let locked = sprite_animations
+            .get(animations_handle)
+            .is_some_and(|animations| animations.is_locked(state, sprite.index));
+
+        if locked || input_buffer.consume(&[PlayerAction::Dash]).is_none() {
+            continue;
+        }
 
+        if stamina.try_spend(STAMINA_DASH_COST) {
+            let direction = if sprite.flip_x { -1. } else { 1. };
+            rb_vel.linvel = Vect::new(direction * **speed * DASH_SPEED_MULTIPLIER, 0.);
+            commands
+                .entity(player)
+                .insert(Dashing(Timer::from_seconds(DASH_DURATION, TimerMode::Once)));
+        }
+    }
+}
+
+/// Deflect the hits for a short time, once the animation allows it.
+fn player_parry(
+    mut commands: Commands,
+
+    sprite_animations: Res<Assets<SpriteAnimations>>,
+    mut player_query: Query<
+        (
+            Entity,
+            &mut InputBuffer,
+            &mut Stamina,
+            &TextureAtlasSprite,
+            &CharacterState,
+            &Handle<SpriteAnimations>,
+        ),
+        (
+            With<Player>,
+            Without<SoulShifting>,
+            Without<ComboPenalty>,
+            Without<Parrying>,
+        ),
+    >,
+) {
+    for (player, mut input_buffer, mut stamina, sprite, state, animations_handle) in
+        player_query.iter_mut()
+    {
+        let locked = sprite_animations
+            .get(animations_handle)
+            .is_some_and(|animations| animations.is_locked(state, sprite.index));
+
+        if locked || input_buffer.consume(&[PlayerAction::Parry]).is_none() {
+            continue;
+        }
+
+        if stamina.try_spend(STAMINA_PARRY_COST) {
+            commands
+                .entity(player)
+                .insert(Parrying(Timer::from_seconds(PARRY_WINDOW, TimerMode::Once)));
+        }
+    }
+}
+
+/// At the end of the dash, the player stops.
+fn dash_timer(
+    mut commands: Commands,
+    time: Res<Time>,
+
+    mut dashing_query: Query<(Entity, &mut Dashing, &mut Velocity)>,
+) {
+    for (character, mut dash, mut rb_vel) in dashing_query.iter_mut() {
+        dash.tick(time.delta());
+
+        if dash.just_finished() {
             rb_vel.linvel = Vect::ZERO;
-            attack_charge.timer.reset();
+            commands.entity(character).remove::<Dashing>();
         }
     }
 }
 
-fn display_player_hp(
-    bleeding_player_query: Query<&Hp, (With<Player>, Or<(Added<Hp>, Changed<Hp>)>)>,
+fn combo_penalty_timer(
+    mut commands: Commands,
+    time: Res<Time>,
+
+    mut penalized_query: Query<(Entity, &mut ComboPenalty)>,
 ) {
-    if let Ok(player_hp) = bleeding_player_query.get_single() {
-        println!("player's hp: {}/{}", player_hp.current, player_hp.max);
+    for (character, mut penalty) in penalized_query.iter_mut() {
+        penalty.tick(time.delta());
+
+        if penalty.just_finished() {
+            commands.entity(character).remove::<ComboPenalty>();
+        }
     }
 }
 
@@ -114,16 +394,19 @@ fn player_death_event(
     mut commands: Commands,
 
     mut possesion_count: ResMut<PossesionCount>,
-    mut player_query: Query<(Entity, &mut Velocity, &mut CharacterState), Without<CrowdMember>>,
+    mut player_query: Query<(Entity, &mut Velocity), Without<CrowdMember>>,
+    mut change_state_event: EventWriter<ChangeStateEvent>,
     // mut soul_shift_event: EventWriter<SoulShiftEvent>,
 ) {
     for player_death in death_event.iter() {
-        // info!("DEATH EVENNNT !!");
         // Death Anim
         match player_query.get_mut(player_death.0) {
             Err(e) => warn!("DEBUG: No player.... {:?}", e),
-            Ok((player, mut rb_vel, mut state)) => {
-                *state = CharacterState::Dead;
+            Ok((player, mut rb_vel)) => {
+                change_state_event.send(ChangeStateEvent {
+                    entity: player,
+                    state: CharacterState::Dead,
+                });
                 rb_vel.linvel.x = 0.;
                 commands
                     .entity(player)
@@ -131,6 +414,11 @@ fn player_death_event(
                         DeadBody,
                         Name::new(format!("DeadBody n°{}", possesion_count.0)),
                         // AnimationTimer(Timer::from_seconds(FRAME_TIME, TimerMode::Once)),
+                        // -- Corpse --
+                        // The corpse stays where it fell
+                        RigidBody::Fixed,
+                        DeathOrder(possesion_count.0),
+                        Reclaimable(Timer::from_seconds(CORPSE_RECLAIM_WINDOW, TimerMode::Once)),
                     ))
                     .remove::<SoulShifting>()
                     .remove::<Player>();
@@ -144,9 +432,22 @@ fn player_death_event(
     }
 }
 
-fn clean_up_dead_bodies(mut commands: Commands, dead_body_query: Query<Entity, Added<DeadBody>>) {
-    for dead_body in dead_body_query.iter() {
-        commands.entity(dead_body).despawn_descendants();
+/// The corpse keeps its hurtbox, out of the fight, and loses its attacks.
+fn clean_up_dead_bodies(
+    mut commands: Commands,
+    dead_body_query: Query<&Children, Added<DeadBody>>,
+    hurtbox_query: Query<(), With<CharacterHitbox>>,
+) {
+    for children in dead_body_query.iter() {
+        for child in children.iter() {
+            if hurtbox_query.contains(*child) {
+                commands
+                    .entity(*child)
+                    .insert(CollisionGroups::new(CORPSE_GROUP, Group::NONE));
+            } else {
+                commands.entity(*child).despawn_recursive();
+            }
+        }
     }
 }
 
@@ -155,35 +456,53 @@ fn clean_up_dead_bodies(mut commands: Commands, dead_body_query: Query<Entity, A
 /// TODO: Movement should be links to the DeltaTime
 /// TODO: Dying while running skip the death animation and the velocity reset
 fn player_movement(
-    keyboard_input: Res<Input<KeyCode>>,
+    controls: Controls,
     mut player_query: Query<
         (
             Entity,
+            &InputMap,
             &Speed,
             &mut Velocity,
             &mut TextureAtlasSprite,
-            &mut CharacterState,
+            &CharacterState,
+            &Handle<SpriteAnimations>,
+        ),
+        (
+            With<Player>,
+            Without<CrowdMember>,
+            Without<SoulShifting>,
+            Without<ComboPenalty>,
+            Without<Dashing>,
         ),
-        (With<Player>, Without<CrowdMember>),
     >,
+    sprite_animations: Res<Assets<SpriteAnimations>>,
     mut flip_direction_event: EventWriter<FlipAttackSensorEvent>,
+    mut change_state_event: EventWriter<ChangeStateEvent>,
 ) {
-    if let Ok((player, speed, mut rb_vel, mut texture_atlas_sprite, mut player_state)) =
-        player_query.get_single_mut()
+    for (
+        player,
+        input_map,
+        speed,
+        mut rb_vel,
+        mut texture_atlas_sprite,
+        player_state,
+        animations_handle,
+    ) in player_query.iter_mut()
     {
-        // If player is attacking, don't allow them to move
-        if *player_state == CharacterState::Attack
-            || *player_state == CharacterState::SecondAttack
-            || *player_state == CharacterState::ChargedAttack
+        // If player is attacking (or getting hit), don't allow them to move
+        // until the recovery frames
+        if sprite_animations
+            .get(animations_handle)
+            .is_some_and(|animations| {
+                animations.is_locked(player_state, texture_atlas_sprite.index)
+            })
         {
             rb_vel.linvel = Vect::ZERO;
-            return;
+            continue;
         }
 
-        let left = keyboard_input.pressed(KeyCode::Q)
-            || keyboard_input.pressed(KeyCode::Left)
-            || keyboard_input.pressed(KeyCode::A);
-        let right = keyboard_input.pressed(KeyCode::D) || keyboard_input.pressed(KeyCode::Right);
+        let left = controls.pressed(input_map, Control::Left);
+        let right = controls.pressed(input_map, Control::Right);
 
         let x_axis = (right as i8) - left as i8;
 
@@ -193,9 +512,15 @@ fn player_movement(
 
         // if there is any movement
         if (left || right) && *player_state != CharacterState::Run {
-            *player_state = CharacterState::Run;
+            change_state_event.send(ChangeStateEvent {
+                entity: player,
+                state: CharacterState::Run,
+            });
         } else if !(left || right) && *player_state == CharacterState::Run {
-            *player_state = CharacterState::Idle;
+            change_state_event.send(ChangeStateEvent {
+                entity: player,
+                state: CharacterState::Idle,
+            });
         }
 
         // ---- Direction ----
@@ -218,25 +543,22 @@ fn player_movement(
 fn spawn_first_player(
     mut commands: Commands,
     mut create_player_event: EventWriter<CreatePlayerEvent>,
-    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
     asset_server: Res<AssetServer>,
 ) {
-    let texture_handle = asset_server.load("textures/character/character_spritesheet_v2.png");
-    let texture_atlas =
-        TextureAtlas::from_grid(texture_handle, Vec2::new(200., 200.), 35, 1, None, None);
-    let texture_atlas_handle = texture_atlases.add(texture_atlas);
-
     let texture_atlas_sprite = TextureAtlasSprite::new(0);
 
     let player_entity = commands
         .spawn((
             Player,
             SpriteSheetBundle {
-                texture_atlas: texture_atlas_handle,
+                texture_atlas: asset_server.load(PLAYER_ANIMATIONS_ATLAS),
                 sprite: texture_atlas_sprite,
                 transform: Transform::from_translation(CHAR_POSITION.into()),
                 ..default()
             },
+            asset_server.load::<SpriteAnimations, _>(PLAYER_ANIMATIONS),
+            InputMap::SOLO,
+            Team::Player,
         ))
         .id();
     create_player_event.send(CreatePlayerEvent(player_entity));
@@ -245,22 +567,27 @@ fn spawn_first_player(
 fn create_player(
     mut create_player_event: EventReader<CreatePlayerEvent>,
     mut commands: Commands,
+    config: Res<GameplayConfig>,
     // mut transform_query: Query<&mut Transform>,
+    body_query: Query<(
+        Option<&Team>,
+        Option<&InputMap>,
+        Option<&Moveset>,
+        Option<&TextureAtlasSprite>,
+    )>,
 ) {
     for CreatePlayerEvent(entity) in create_player_event.iter() {
-        let mut animation_indices = AnimationIndices(HashMap::new());
-        animation_indices.insert(CharacterState::Idle, PLAYER_IDLE_FRAMES);
-        animation_indices.insert(CharacterState::Run, PLAYER_RUN_FRAMES);
-        animation_indices.insert(
-            CharacterState::TransitionToCharge,
-            PLAYER_TRANSITION_TO_CHARGE_FRAMES,
-        );
-        animation_indices.insert(CharacterState::Charge, PLAYER_CHARGE_FRAMES);
-        animation_indices.insert(CharacterState::Attack, PLAYER_FIRST_ATTACK_FRAMES);
-        animation_indices.insert(CharacterState::SecondAttack, PLAYER_SECOND_ATTACK_FRAMES);
-        animation_indices.insert(CharacterState::ChargedAttack, PLAYER_FULL_ATTACK_FRAMES);
-        animation_indices.insert(CharacterState::Hit, PLAYER_HIT_FRAMES);
-        animation_indices.insert(CharacterState::Dead, PLAYER_DEAD_FRAMES);
+        // Given by the previous body, see `soul_shift::start_soul_shift`
+        let (team, input_map, moveset, sprite) = body_query.get(*entity).unwrap_or_default();
+        let team = team.copied().unwrap_or_default();
+        // Kept by the body, see `characters::moveset`
+        let moveset = moveset.copied().unwrap_or_default();
+        // The attack sensors are spawned facing right
+        let facing = if sprite.is_some_and(|sprite| sprite.flip_x) {
+            -1.
+        } else {
+            1.
+        };
 
         // match transform_query.get_mut(*entity) {
         //     Err(e) => warn!("No transform in the entity, wat the freak: {:?}", e),
@@ -281,17 +608,20 @@ fn create_player(
                 Name::new("Player"),
                 // -- Animation --
                 AnimationTimer(Timer::from_seconds(FRAME_TIME, TimerMode::Repeating)),
-                animation_indices,
                 CharacterState::default(),
                 // -- Combat --
                 // Hp::default(),
-                Hp::new(20),
-                Invulnerable(Timer::from_seconds(10., TimerMode::Once)),
+                Hp::new(moveset.hp(&config)),
+                moveset.damage_policy(&config),
+                Invulnerable(Timer::from_seconds(
+                    config.player.spawn_invulnerability,
+                    TimerMode::Once,
+                )),
                 // -- Hitbox --
                 RigidBody::Dynamic,
                 LockedAxes::ROTATION_LOCKED,
                 MovementBundle {
-                    speed: Speed::default(),
+                    speed: Speed(moveset.speed(&config)),
                     velocity: Velocity {
                         linvel: Vect::ZERO,
                         angvel: 0.,
@@ -300,76 +630,30 @@ fn create_player(
                 // -- Attack --
                 AttackCharge {
                     charging: false,
-                    timer: Timer::from_seconds(CHARGED_ATTACK_HOLD, TimerMode::Once),
+                    timer: Timer::from_seconds(config.player.charged_attack_hold, TimerMode::Once),
                 },
+                Combo::default(),
+                InputBuffer::default(),
+                Stamina::new(STAMINA_MAX),
             ))
-            .remove::<SoulShifting>()
+            // -- Controls --
+            .insert((team, input_map.copied().unwrap_or_default(), moveset))
+            // The crowd's variety
+            .remove::<(SoulShifting, PlaybackSpeed)>()
             .with_children(|parent| {
                 // -- Player Hitbox And Sensor --
                 // TODO: seperate the player Sensor to the player hitbox
                 // ^^^^^-------- Sensor that will trigger the boss attack
                 // And Hitbox which designates where is it precisely --^^
-                parent.spawn((
-                    Collider::ball(PLAYER_HITBOX_SIZE),
-                    Transform::from_translation(PLAYER_HITBOX_OFFSET_Y.into()),
-                    PlayerHitbox,
-                    CharacterHitbox,
-                    Sensor,
-                    // ActiveEvents::COLLISION_EVENTS,
-                    Name::new("Player Hitbox"),
-                ));
+                parent.spawn((moveset.hurtbox(team), PlayerHitbox));
 
                 // -- Attack Hitbox --
-                // TODO: Active the sensor only for certain frame
-                parent
-                    .spawn((
-                        SpatialBundle {
-                            transform: Transform::from_translation(BOTTOM_WHIP_POS.into()),
-                            ..default()
-                        },
-                        AttackSensor,
-                        RigidBody::Dynamic,
-                        Name::new("Parent Bottom Whip"),
-                    ))
-                    .with_children(|parent| {
-                        // Thin bottom Whip
-                        parent.spawn((
-                            // REFACTOR: Find a way to .into() a (f32, f32) tuple into a 2arguments function
-                            Collider::cuboid(
-                                PLAYER_ATTACK_HITBOX_BOTTOM.0,
-                                PLAYER_ATTACK_HITBOX_BOTTOM.1,
-                            ),
-                            Transform::default(),
-                            AttackHitbox(10),
-                            Sensor,
-                            Name::new("Attack Hitbox: Sensor Bottom Whip"),
-                        ));
-                    });
-
-                parent
-                    .spawn((
-                        SpatialBundle {
-                            transform: Transform::from_translation(FRONT_WHIP_POS.into()),
-                            ..default()
-                        },
-                        AttackSensor,
-                        RigidBody::Dynamic,
-                        Name::new("Parent Front Ball"),
-                    ))
-                    .with_children(|parent| {
-                        // Front Ball
-                        parent.spawn((
-                            // REFACTOR: Find a way to .into() a (f32, f32) tuple into a 2arguments function
-                            Collider::cuboid(
-                                PLAYER_ATTACK_HITBOX_FRONT.0,
-                                PLAYER_ATTACK_HITBOX_FRONT.1,
-                            ),
-                            Transform::default(),
-                            AttackHitbox(10),
-                            Sensor,
-                            Name::new("Attack Hitbox: Sensor Front Ball"),
-                        ));
-                    });
+                moveset.spawn_attack_hitboxes(parent, team, facing, &config);
             });
+
+        // Only the boss tanks the hits
+        if let Some(poise) = moveset.poise() {
+            commands.entity(*entity).insert(poise);
+        }
     }
 }
@@ -0,0 +1,101 @@
+//! Stamina
+//!
+//! Spent by attacks, charge holding, dash and parry.
+//! Regenerates after a short delay without spending it.
+
+use bevy::prelude::*;
+
+use crate::{
+    characters::{
+        aggression::AttackCharge, animations::CharacterState, state_machine::ChangeStateEvent,
+    },
+    constants::character::player::{STAMINA_CHARGE_DRAIN, STAMINA_REGEN, STAMINA_REGEN_DELAY},
+    simulation::GameplayApp,
+};
+
+pub struct StaminaPlugin;
+
+impl Plugin for StaminaPlugin {
+    #[rustfmt::skip]
+    fn build(&self, app: &mut App) {
+        app .add_gameplay_system(stamina_regeneration)
+            .add_gameplay_system(charge_stamina_drain.before("Change State"))
+            ;
+    }
+}
+
+#[derive(Component, Debug)]
+pub struct Stamina {
+    pub current: f32,
+    pub max: f32,
+    /// Regeneration starts when it finishes
+    pub regen_delay: Timer,
+}
+
+impl Stamina {
+    pub fn new(max: f32) -> Stamina {
+        Stamina {
+            current: max,
+            max,
+            regen_delay: Timer::from_seconds(STAMINA_REGEN_DELAY, TimerMode::Once),
+        }
+    }
+
+    /// Empty: no more action
+    pub fn is_empty(&self) -> bool {
+        self.current <= 0.
+    }
+
+    /// Spend what is left, up to `cost`
+    pub fn spend(&mut self, cost: f32) {
+        self.current = (self.current - cost).max(0.);
+        self.regen_delay.reset();
+    }
+
+    /// Spend `cost` only if there is enough of it
+    pub fn try_spend(&mut self, cost: f32) -> bool {
+        if self.current >= cost {
+            self.spend(cost);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+fn stamina_regeneration(time: Res<Time>, mut stamina_query: Query<&mut Stamina>) {
+    for mut stamina in stamina_query.iter_mut() {
+        stamina.regen_delay.tick(time.delta());
+
+        if stamina.regen_delay.finished() && stamina.current < stamina.max {
+            stamina.current =
+                (stamina.current + STAMINA_REGEN * time.delta_seconds()).min(stamina.max);
+        }
+    }
+}
+
+/// Holding the charge is tiring.
+///
+/// Out of breath, the charge is dropped.
+fn charge_stamina_drain(
+    time: Res<Time>,
+    mut charging_query: Query<(Entity, &mut Stamina, &mut AttackCharge)>,
+    mut change_state_event: EventWriter<ChangeStateEvent>,
+) {
+    for (character, mut stamina, mut attack_charge) in charging_query.iter_mut() {
+        if !attack_charge.charging {
+            continue;
+        }
+
+        stamina.spend(STAMINA_CHARGE_DRAIN * time.delta_seconds());
+
+        if stamina.is_empty() {
+            attack_charge.charging = false;
+            attack_charge.timer.reset();
+            change_state_event.send(ChangeStateEvent {
+                entity: character,
+                state: CharacterState::Idle,
+            });
+        }
+    }
+}
@@ -0,0 +1,138 @@
+//! Character State Machine
+//!
+//! Systems don't overwrite the `CharacterState` of a character,
+//! they ask for a new one by sending a `ChangeStateEvent`.
+//!
+//! The transition is granted according to the priority of the states
+//! and to the recovery frame of the current clip (see `AnimationClip::recovery`).
+//! The end of a clip (`ClipEnd`) is handled by `animate_character` itself.
+
+use bevy::prelude::*;
+
+use crate::{
+    characters::animations::{CharacterState, SpriteAnimations},
+    combat_log::{CombatEvent, CombatLogger},
+};
+
+/// Can the current state be interrupted by the next one ?
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interrupt {
+    Always,
+    /// Once the current clip reached its recovery frame
+    AfterRecovery,
+    Never,
+}
+
+impl CharacterState {
+    /// Among the requests of a same frame, the highest one wins.
+    ///
+    /// A state can be interrupted at any time by a higher one.
+    pub fn priority(&self) -> u8 {
+        match self {
+            CharacterState::Idle | CharacterState::Run => 0,
+            CharacterState::TransitionToCharge | CharacterState::Charge => 1,
+            CharacterState::Attack
+            | CharacterState::SecondAttack
+            | CharacterState::ChargedAttack => 2,
+            CharacterState::Hit => 3,
+            CharacterState::Stunned => 4,
+            CharacterState::Dead => 5,
+        }
+    }
+
+    /// The transition table.
+    pub fn interrupt(&self, next: &CharacterState) -> Interrupt {
+        match (self, next) {
+            // Only a soul shift can raise the dead, see `soul_shift::join_the_fight`
+            (CharacterState::Dead, _) => Interrupt::Never,
+            (_, CharacterState::Dead) => Interrupt::Always,
+            // Looping states are not restarted
+            (CharacterState::Idle, CharacterState::Idle)
+            | (CharacterState::Run, CharacterState::Run)
+            | (CharacterState::Charge, CharacterState::Charge) => Interrupt::Never,
+            _ if next.priority() > self.priority() => Interrupt::Always,
+            // e.g. attacks lock the movement until their recovery frames
+            _ => Interrupt::AfterRecovery,
+        }
+    }
+}
+
+/// Happens when
+///   - characters::player::player_attack
+///     - Charge / Attack / Charged Attack
+///   - characters::player::player_movement
+///     - Run / Idle
+///   - characters::player::player_death_event
+///   - soul_shift::start_soul_shift
+///     - Dead
+///   - characters::aggression::damage_hit
+///     - Hit / Stunned (poise broken)
+///   - characters::npcs::boss::aggression::boss_attack_event_handler
+///     - Attack
+///   - characters::npcs::boss::aggression::boss_scripted_attacks
+///     - Looped / Mirrored attack (training)
+///
+/// Read in
+///   - characters::state_machine::change_state
+///     - The character enters this state, if the transition is allowed
+#[derive(Debug, Clone)]
+pub struct ChangeStateEvent {
+    pub entity: Entity,
+    pub state: CharacterState,
+}
+
+/// Apply the allowed transitions.
+///
+/// Only the highest priority request of each character is considered.
+pub fn change_state(
+    mut change_state_event: EventReader<ChangeStateEvent>,
+
+    sprite_animations: Res<Assets<SpriteAnimations>>,
+    mut character_query: Query<(
+        &mut CharacterState,
+        &TextureAtlasSprite,
+        Option<&Handle<SpriteAnimations>>,
+    )>,
+    mut combat_log: CombatLogger,
+) {
+    // In the order of the events, for the combat log (and the replays) to stay the same
+    let mut requests: Vec<(Entity, CharacterState)> = Vec::new();
+    for ChangeStateEvent { entity, state } in change_state_event.iter() {
+        match requests
+            .iter_mut()
+            .find(|(character, _)| character == entity)
+        {
+            Some((_, request)) if request.priority() >= state.priority() => {}
+            Some((_, request)) => *request = state.clone(),
+            None => requests.push((*entity, state.clone())),
+        }
+    }
+
+    for (character, next_state) in requests {
+        match character_query.get_mut(character) {
+            Err(e) => warn!("This entity {:?} has no state: {:?}", character, e),
+            Ok((mut state, sprite, animations_handle)) => {
+                let allowed = match state.interrupt(&next_state) {
+                    Interrupt::Always => true,
+                    Interrupt::Never => false,
+                    Interrupt::AfterRecovery => {
+                        // Still loading: nothing to lock
+                        match animations_handle.and_then(|handle| sprite_animations.get(handle)) {
+                            None => true,
+                            Some(animations) => !animations.is_locked(&state, sprite.index),
+                        }
+                    }
+                };
+
+                if allowed {
+                    combat_log.log(CombatEvent::StateChange {
+                        entity: character,
+                        from: state.clone(),
+                        to: next_state.clone(),
+                    });
+                    *state = next_state;
+                }
+            }
+        }
+    }
+}
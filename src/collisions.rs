@@ -33,3 +33,81 @@ impl CollisionEventExt for CollisionEvent {
         !self.is_started()
     }
 }
+
+// -- Collision Groups --
+// An attack hitbox (`AttackHitbox`) only meets the hurtboxes (`CharacterHitbox`) of the other side.
+// Rapier doesn't even consider the other pairs:
+// the crowd and the corpses can never be hit by accident.
+
+/// Attack hitboxes of the player
+pub const PLAYER_ATTACK_GROUP: Group = Group::GROUP_1;
+/// Attack hitboxes and range sensor of the boss
+pub const BOSS_ATTACK_GROUP: Group = Group::GROUP_2;
+/// Hurtbox of the player
+pub const PLAYER_HURT_GROUP: Group = Group::GROUP_3;
+/// Hurtbox of the boss
+pub const BOSS_HURT_GROUP: Group = Group::GROUP_4;
+/// Meets no one
+pub const CROWD_GROUP: Group = Group::GROUP_5;
+/// Hurtbox of the corpses: meets no one either
+pub const CORPSE_GROUP: Group = Group::GROUP_6;
+
+/// The hurtboxes met by the player: only the boss's attacks
+pub fn player_hurtbox_groups() -> CollisionGroups {
+    CollisionGroups::new(PLAYER_HURT_GROUP, BOSS_ATTACK_GROUP)
+}
+
+/// The hurtboxes met by the boss: only the player's attacks
+pub fn boss_hurtbox_groups() -> CollisionGroups {
+    CollisionGroups::new(BOSS_HURT_GROUP, PLAYER_ATTACK_GROUP)
+}
+
+/// The side of a fighter: the attacks which hurt them
+/// and the hurtboxes their own attacks meet.
+///
+/// The second player of a versus fights in the boss team (see `versus`).
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Team {
+    #[default]
+    Player,
+    Boss,
+}
+
+impl Team {
+    pub fn opponent(self) -> Team {
+        match self {
+            Team::Player => Team::Boss,
+            Team::Boss => Team::Player,
+        }
+    }
+
+    pub fn hurtbox_groups(self) -> CollisionGroups {
+        match self {
+            Team::Player => player_hurtbox_groups(),
+            Team::Boss => boss_hurtbox_groups(),
+        }
+    }
+
+    /// Of the attack hitboxes
+    pub fn attack_group(self) -> Group {
+        match self {
+            Team::Player => PLAYER_ATTACK_GROUP,
+            Team::Boss => BOSS_ATTACK_GROUP,
+        }
+    }
+
+    /// The hurtboxes met by the attacks
+    pub fn attack_targets(self) -> Group {
+        match self.opponent() {
+            Team::Player => PLAYER_HURT_GROUP,
+            Team::Boss => BOSS_HURT_GROUP,
+        }
+    }
+}
+
+/// An attack hitbox starts inactive: it meets no one.
+///
+/// See `characters::aggression::AttackTargets`
+pub fn attack_hitbox_groups(attack_group: Group) -> CollisionGroups {
+    CollisionGroups::new(attack_group, Group::NONE)
+}
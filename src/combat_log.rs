@@ -0,0 +1,256 @@
+//! Combat Log
+//!
+//! Every hit, soul shift and state change of the fight, with its tick.
+//! Shown in the debug panel (see `debug`), exported as JSON or CSV for the balancing:
+//! from the panel, or at the end of each fight and on exit.
+//!
+//! ```text
+//! grandstand-gauntlet --combat-log fight.csv
+//! ```
+
+use std::{
+    fmt,
+    marker::PhantomData,
+    path::{Path, PathBuf},
+};
+
+use bevy::{app::AppExit, ecs::system::SystemParam, prelude::*};
+use serde::{Serialize, Serializer};
+
+use crate::{
+    characters::{animations::CharacterState, player::PlayerDeathEvent},
+    simulation::{GameplayApp, SimulationTick},
+};
+
+#[derive(Default)]
+pub struct CombatLogPlugin {
+    /// Where the log is exported, see `CombatLogExport`
+    pub export_to: Option<PathBuf>,
+}
+
+impl CombatLogPlugin {
+    /// `--combat-log <file>`, the other arguments are ignored.
+    pub fn from_args() -> Result<Self, MissingPath> {
+        CombatLogPlugin::parse_args(std::env::args().skip(1))
+    }
+
+    /// See `from_args`
+    pub fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Self, MissingPath> {
+        let mut plugin = CombatLogPlugin::default();
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            if arg == "--combat-log" {
+                plugin.export_to = Some(args.next().ok_or(MissingPath)?.into());
+            }
+        }
+
+        Ok(plugin)
+    }
+}
+
+impl Plugin for CombatLogPlugin {
+    #[rustfmt::skip]
+    fn build(&self, app: &mut App) {
+        app .init_resource::<CombatLog>()
+            .insert_resource(CombatLogExport(self.export_to.clone()))
+            .add_gameplay_system(export_on_death.after("Player Death"))
+            .add_system_to_stage(CoreStage::Last, export_on_exit)
+            ;
+    }
+}
+
+/// `--combat-log` without its file
+#[derive(Debug)]
+pub struct MissingPath;
+
+impl fmt::Display for MissingPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "--combat-log needs a file")
+    }
+}
+
+impl std::error::Error for MissingPath {}
+
+/// The file the `CombatLog` is exported in at the end of each fight and on exit, if any.
+///
+/// As JSON or CSV, according to its extension (see `CombatLog::export`).
+#[derive(Resource, Debug, Default, Clone)]
+pub struct CombatLogExport(pub Option<PathBuf>);
+
+#[derive(Resource, Debug, Default, Clone, Serialize)]
+pub struct CombatLog {
+    pub entries: Vec<CombatLogEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CombatLogEntry {
+    pub tick: u64,
+    #[serde(flatten)]
+    pub event: CombatEvent,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind")]
+pub enum CombatEvent {
+    /// Only the hits landing on a vulnerable target
+    Hit {
+        #[serde(serialize_with = "entity_id")]
+        attacker: Entity,
+        #[serde(serialize_with = "entity_id")]
+        target: Entity,
+        #[serde(serialize_with = "entity_id")]
+        hitbox: Entity,
+        /// After the combo scaling and the damage policy of the target
+        damage: i32,
+        hp_before: i32,
+        hp_after: i32,
+    },
+    SoulShift {
+        /// The dying body
+        #[serde(serialize_with = "entity_id")]
+        from: Entity,
+        /// The spectator (or corpse) the soul travels to
+        #[serde(serialize_with = "entity_id")]
+        to: Entity,
+    },
+    StateChange {
+        #[serde(serialize_with = "entity_id")]
+        entity: Entity,
+        from: CharacterState,
+        to: CharacterState,
+    },
+}
+
+/// As displayed by bevy, `12v0`
+fn entity_id<S: Serializer>(entity: &Entity, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(&format_args!("{:?}", entity))
+}
+
+impl fmt::Display for CombatLogEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] ", self.tick)?;
+        match &self.event {
+            CombatEvent::Hit {
+                attacker,
+                target,
+                damage,
+                hp_before,
+                hp_after,
+                ..
+            } => write!(
+                f,
+                "{:?} hits {:?} for {} ({} -> {})",
+                attacker, target, damage, hp_before, hp_after
+            ),
+            CombatEvent::SoulShift { from, to } => {
+                write!(f, "Soul shift from {:?} to {:?}", from, to)
+            }
+            CombatEvent::StateChange { entity, from, to } => {
+                write!(f, "{:?}: {:?} -> {:?}", entity, from, to)
+            }
+        }
+    }
+}
+
+impl CombatLog {
+    pub fn hits(&self) -> impl Iterator<Item = &CombatLogEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| matches!(entry.event, CombatEvent::Hit { .. }))
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(&self.entries)
+    }
+
+    /// One row per entry, the columns which don't concern its kind are left empty.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(
+            "tick,kind,attacker,target,hitbox,damage,hp_before,hp_after,entity,from,to\n",
+        );
+
+        for CombatLogEntry { tick, event } in &self.entries {
+            let row = match event {
+                CombatEvent::Hit {
+                    attacker,
+                    target,
+                    hitbox,
+                    damage,
+                    hp_before,
+                    hp_after,
+                } => format!(
+                    "Hit,{:?},{:?},{:?},{},{},{},,,",
+                    attacker, target, hitbox, damage, hp_before, hp_after
+                ),
+                CombatEvent::SoulShift { from, to } => {
+                    format!("SoulShift,,,,,,,,{:?},{:?}", from, to)
+                }
+                CombatEvent::StateChange { entity, from, to } => {
+                    format!("StateChange,,,,,,,{:?},{:?},{:?}", entity, from, to)
+                }
+            };
+            csv.push_str(&format!("{},{}\n", tick, row));
+        }
+
+        csv
+    }
+
+    /// As JSON or CSV, according to the extension of the file
+    pub fn export(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        let path = path.as_ref();
+        let text = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("csv") => self.to_csv(),
+            _ => self.to_json()?,
+        };
+        std::fs::write(path, text)
+    }
+}
+
+/// Stamps the entries with the current tick.
+#[derive(SystemParam)]
+pub struct CombatLogger<'w, 's> {
+    tick: Res<'w, SimulationTick>,
+    combat_log: ResMut<'w, CombatLog>,
+    #[system_param(ignore)]
+    _marker: PhantomData<&'s ()>,
+}
+
+impl<'w, 's> CombatLogger<'w, 's> {
+    pub fn log(&mut self, event: CombatEvent) {
+        let tick = **self.tick;
+        self.combat_log.entries.push(CombatLogEntry { tick, event });
+    }
+}
+
+/// A death ends the fight of the body, or the round in versus.
+///
+/// The file is overwritten each time: the last export holds the whole fight.
+fn export_on_death(
+    mut death_event: EventReader<PlayerDeathEvent>,
+    export: Res<CombatLogExport>,
+    combat_log: Res<CombatLog>,
+) {
+    if death_event.iter().last().is_some() {
+        export_combat_log(&combat_log, &export);
+    }
+}
+
+fn export_on_exit(
+    mut app_exit_events: EventReader<AppExit>,
+    export: Res<CombatLogExport>,
+    combat_log: Res<CombatLog>,
+) {
+    if app_exit_events.iter().next().is_some() {
+        export_combat_log(&combat_log, &export);
+    }
+}
+
+fn export_combat_log(combat_log: &CombatLog, CombatLogExport(path): &CombatLogExport) {
+    if let Some(path) = path {
+        match combat_log.export(path) {
+            Ok(()) => info!("Combat log exported in {}", path.display()),
+            Err(e) => error!("Can't export the combat log in {}: {}", path.display(), e),
+        }
+    }
+}
@@ -0,0 +1,243 @@
+//! Gameplay Config
+//!
+//! The balancing values of the fight are read from `assets/config/gameplay.config.ron`,
+//! loaded at startup and reloaded each time the file is saved:
+//! the fight can be tuned without recompiling.
+//!
+//! Until the file is loaded (and without it, in the tests), the `Default` values are used.
+//! A reload is applied at the start of the next tick, to the characters already spawned.
+
+use bevy::{
+    asset::{AssetLoader, BoxedFuture, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    characters::{
+        aggression::{AttackCharge, AttackHitbox, AttackHitboxKind, DamagePolicy, Hp},
+        movement::Speed,
+        moveset::Moveset,
+        npcs::boss::Boss,
+        player::Player,
+    },
+    constants::{
+        character::{
+            boss::{BOSS_HIT_INVULNERABILITY, BOSS_HP, BOSS_SMASH_COOLDOWN},
+            player::{CHARGED_ATTACK_HOLD, PLAYER_HIT_INVULNERABILITY},
+        },
+        config::GAMEPLAY_CONFIG,
+        crowd::{CROWD_ROW_DENSITY, CROWD_SEATS_BY_ROW},
+        TILE_SIZE,
+    },
+    simulation::{GameplayApp, TickStage},
+};
+
+pub struct ConfigPlugin;
+
+impl Plugin for ConfigPlugin {
+    #[rustfmt::skip]
+    fn build(&self, app: &mut App) {
+        app .init_resource::<GameplayConfig>()
+            .add_asset::<GameplayConfig>()
+            .init_asset_loader::<GameplayConfigLoader>()
+            .add_startup_system(load_config)
+            .add_system_to_stage(CoreStage::PreUpdate, reload_config)
+            .add_gameplay_system_to_stage(TickStage::First, apply_config.label("Apply Config"))
+            ;
+    }
+}
+
+/// The values in use, as a resource,
+/// and the content of the config file, as an asset.
+///
+/// A missing field keeps its default value.
+#[derive(Resource, Debug, Clone, PartialEq, Default, Serialize, Deserialize, TypeUuid)]
+#[uuid = "9c4f3e2a-6b1d-4f7e-8a35-1d2c7b9e0f46"]
+#[serde(default)]
+pub struct GameplayConfig {
+    pub player: PlayerConfig,
+    pub boss: BossConfig,
+    pub crowd: CrowdConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlayerConfig {
+    pub hp: i32,
+    /// In tiles per second
+    pub speed: f32,
+    /// Of each light swing, before the combo scaling
+    pub attack_damage: i32,
+    /// Hold of the attack button to charge an attack, in seconds
+    pub charged_attack_hold: f32,
+    /// Invulnerability after a hit, in seconds
+    pub hit_invulnerability: f32,
+    /// Invulnerability of a fresh body, in seconds
+    pub spawn_invulnerability: f32,
+}
+
+impl Default for PlayerConfig {
+    fn default() -> Self {
+        PlayerConfig {
+            hp: 20,
+            speed: Speed::default().0 / TILE_SIZE,
+            attack_damage: 10,
+            charged_attack_hold: CHARGED_ATTACK_HOLD,
+            hit_invulnerability: PLAYER_HIT_INVULNERABILITY,
+            spawn_invulnerability: 10.,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BossConfig {
+    pub hp: i32,
+    /// In tiles per second
+    pub speed: f32,
+    /// Of each attack hitbox
+    pub attack_damage: i32,
+    /// Time between two smashes, in seconds
+    pub smash_cooldown: f32,
+    /// Invulnerability after a hit, in seconds
+    pub hit_invulnerability: f32,
+}
+
+impl Default for BossConfig {
+    fn default() -> Self {
+        BossConfig {
+            hp: BOSS_HP,
+            speed: Speed::default().0 / TILE_SIZE,
+            attack_damage: 10,
+            smash_cooldown: BOSS_SMASH_COOLDOWN,
+            hit_invulnerability: BOSS_HIT_INVULNERABILITY,
+        }
+    }
+}
+
+/// Size of the crowd.
+///
+/// Changing it seats a new crowd (see `crowd::reseat_crowd`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CrowdConfig {
+    /// Chance for a seat to be taken, from the front row to the back one:
+    /// one row each
+    pub row_density: Vec<f32>,
+    /// Seats of each row, aisles included, centered on the arena
+    pub seats_by_row: usize,
+}
+
+impl Default for CrowdConfig {
+    fn default() -> Self {
+        CrowdConfig {
+            row_density: CROWD_ROW_DENSITY.to_vec(),
+            seats_by_row: CROWD_SEATS_BY_ROW,
+        }
+    }
+}
+
+impl GameplayConfig {
+    /// Damage of an attack hitbox, according to its owner
+    pub fn attack_damage(&self, kind: AttackHitboxKind) -> i32 {
+        match kind {
+            AttackHitboxKind::BottomWhip | AttackHitboxKind::FrontBall => self.player.attack_damage,
+            AttackHitboxKind::Smash | AttackHitboxKind::FallenAngel => self.boss.attack_damage,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct GameplayConfigLoader;
+
+impl AssetLoader for GameplayConfigLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let config: GameplayConfig = ron::de::from_bytes(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(config));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["config.ron"]
+    }
+}
+
+/// Keeps the config file loaded, and watched.
+#[derive(Resource, Deref)]
+pub struct GameplayConfigHandle(pub Handle<GameplayConfig>);
+
+fn load_config(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(GameplayConfigHandle(asset_server.load(GAMEPLAY_CONFIG)));
+}
+
+/// Replaces the config in use by the file, once loaded and after each modification.
+fn reload_config(
+    mut asset_events: EventReader<AssetEvent<GameplayConfig>>,
+    configs: Res<Assets<GameplayConfig>>,
+    handle: Res<GameplayConfigHandle>,
+    mut config: ResMut<GameplayConfig>,
+) {
+    for event in asset_events.iter() {
+        match event {
+            AssetEvent::Created { handle: loaded } | AssetEvent::Modified { handle: loaded }
+                if *loaded == **handle =>
+            {
+                match configs.get(loaded) {
+                    None => warn!("The gameplay config is gone"),
+                    // Untouched: no need to apply it again
+                    Some(loaded) if *loaded == *config => {}
+                    Some(loaded) => {
+                        info!("Gameplay config loaded");
+                        *config = loaded.clone();
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The new values apply to the characters already in the fight,
+/// according to their `Moveset`: a possessed boss keeps the boss's stats.
+///
+/// The timers running (cooldowns, invulnerability) finish with their old duration.
+fn apply_config(
+    config: Res<GameplayConfig>,
+    mut character_query: Query<
+        (
+            &Moveset,
+            &mut Hp,
+            &mut Speed,
+            &mut DamagePolicy,
+            Option<&mut AttackCharge>,
+        ),
+        Or<(With<Player>, With<Boss>)>,
+    >,
+    mut attack_hitbox_query: Query<(&mut AttackHitbox, &AttackHitboxKind)>,
+) {
+    if !config.is_changed() {
+        return;
+    }
+
+    for (moveset, mut hp, mut speed, mut damage_policy, attack_charge) in &mut character_query {
+        hp.set_max(moveset.hp(&config));
+        speed.0 = moveset.speed(&config);
+        damage_policy.invulnerability = moveset.hit_invulnerability(&config);
+        if let Some(mut attack_charge) = attack_charge {
+            let hold = std::time::Duration::from_secs_f32(config.player.charged_attack_hold);
+            attack_charge.timer.set_duration(hold);
+        }
+    }
+
+    for (mut attack_hitbox, kind) in &mut attack_hitbox_query {
+        attack_hitbox.0 = config.attack_damage(*kind);
+    }
+}
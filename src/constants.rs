@@ -14,10 +14,54 @@ pub mod character {
     pub const CHAR_Z: f32 = 10.;
     pub const CHAR_POSITION: (f32, f32, f32) = (0., -60., CHAR_Z);
 
+    /// The invulnerable characters blink between opaque and this alpha
+    pub const INVULNERABLE_ALPHA: f32 = 0.3;
+    /// Duration of a full blink
+    pub const INVULNERABLE_BLINK_PERIOD: f32 = 0.2;
+
+    // -- Poise --
+    /// Stagger dealt by a charged attack, relative to its damage
+    pub const CHARGED_ATTACK_STAGGER_MULTIPLIER: f32 = 2.5;
+
     pub mod player {
         pub const BOTTOM_WHIP_POS: (f32, f32, f32) = (10., -5.5, 0.);
         pub const FRONT_WHIP_POS: (f32, f32, f32) = (30., -3., 0.);
+        /// Default of the `config::GameplayConfig`
         pub const CHARGED_ATTACK_HOLD: f32 = 0.5;
+        /// How long an input waits for the animation to accept it, in seconds
+        pub const INPUT_BUFFER_DURATION: f64 = 0.25;
+
+        // -- Combo --
+        /// Damage multiplier applied for each chained swing
+        pub const COMBO_DAMAGE_SCALING: f32 = 0.8;
+        pub const COMBO_MIN_DAMAGE_SCALE: f32 = 0.5;
+        /// Time without moving nor attacking after dropping a combo
+        pub const COMBO_DROP_PENALTY: f32 = 0.4;
+
+        // -- Stamina --
+        pub const STAMINA_MAX: f32 = 100.;
+        /// Per second
+        pub const STAMINA_REGEN: f32 = 35.;
+        /// Time without spending before regenerating
+        pub const STAMINA_REGEN_DELAY: f32 = 0.6;
+        pub const STAMINA_ATTACK_COST: f32 = 15.;
+        pub const STAMINA_CHARGED_ATTACK_COST: f32 = 20.;
+        /// Per second, while holding the charge
+        pub const STAMINA_CHARGE_DRAIN: f32 = 20.;
+        pub const STAMINA_DASH_COST: f32 = 25.;
+        pub const STAMINA_PARRY_COST: f32 = 20.;
+
+        // -- Dash / Parry --
+        pub const DASH_DURATION: f32 = 0.15;
+        pub const DASH_SPEED_MULTIPLIER: f32 = 3.;
+        pub const PARRY_WINDOW: f32 = 0.25;
+
+        // -- Damage Policy --
+        /// Generous: the player can't be hit twice by the same smash
+        /// (default of the `config::GameplayConfig`)
+        pub const PLAYER_HIT_INVULNERABILITY: f32 = 2.;
+        pub const PLAYER_ARMOR: i32 = 0;
+        pub const PLAYER_DAMAGE_MULTIPLIER: f32 = 1.;
 
         pub const PLAYER_HITBOX_SIZE: f32 = 12.;
         pub const PLAYER_HITBOX_OFFSET_Y: (f32, f32, f32) = (0., 2., 0.);
@@ -25,23 +69,33 @@ pub mod character {
         pub const PLAYER_ATTACK_HITBOX_FRONT: (f32, f32) = (20., 7.);
 
         // -- Animation --
-        pub const PLAYER_IDLE_FRAMES: (usize, usize) = (0, 4);
-        pub const PLAYER_RUN_FRAMES: (usize, usize) = (5, 12);
-        pub const PLAYER_TRANSITION_TO_CHARGE_FRAMES: (usize, usize) = (13, 14);
-        pub const PLAYER_CHARGE_FRAMES: (usize, usize) = (15, 18);
-        // TODO: Seperate Charge and Attack to perfectly activate the hitbox
-        pub const PLAYER_FULL_ATTACK_FRAMES: (usize, usize) = (19, 26);
-        // First Swing
-        pub const PLAYER_FIRST_ATTACK_FRAMES: (usize, usize) = (19, 22);
-        pub const PLAYER_SECOND_ATTACK_FRAMES: (usize, usize) = (23, 26);
-        pub const PLAYER_HIT_FRAMES: (usize, usize) = (27, 28);
-        pub const PLAYER_DEAD_FRAMES: (usize, usize) = (29, 34);
+        pub const PLAYER_ANIMATIONS: &str = "animations/player.anim.ron";
+        pub const PLAYER_ANIMATIONS_ATLAS: &str = "animations/player.anim.ron#atlas";
     }
 
     pub mod boss {
+        /// Identifies the boss in a replay
+        pub const BOSS_ID: &str = "boss";
+
+        /// Default of the `config::GameplayConfig`
         pub const BOSS_HP: i32 = 1000;
+        // -- Damage Policy --
+        /// Short: allows the combos, but not two hits from the same swing
+        /// (default of the `config::GameplayConfig`)
+        pub const BOSS_HIT_INVULNERABILITY: f32 = 0.3;
+        pub const BOSS_ARMOR: i32 = 0;
+        pub const BOSS_DAMAGE_MULTIPLIER: f32 = 1.;
+        /// Stagger to accumulate to stun the boss
+        pub const BOSS_POISE: f32 = 40.;
+        /// Per second
+        pub const BOSS_POISE_REGEN: f32 = 10.;
+        /// Time without being hit before regenerating
+        pub const BOSS_POISE_REGEN_DELAY: f32 = 1.5;
 
+        /// Default of the `config::GameplayConfig`
         pub const BOSS_SMASH_COOLDOWN: f32 = 5.;
+        pub const BOSS_SMASH_HIT_STOP: f32 = 0.12;
+        pub const BOSS_SMASH_SHAKE_PER_DAMAGE: f32 = 0.3;
 
         pub const FRONT_SMASH_POS_TOP: (f32, f32, f32) = (42., 11., 0.);
         pub const BOSS_ATTACK_HITBOX_SMASH_TOP: (f32, f32) = (10., 5.);
@@ -56,21 +110,179 @@ pub mod character {
         pub const BOSS_HITBOX_OFFSET_Y: (f32, f32, f32) = (0., 5., 0.);
 
         // -- Animation --
-        pub const BOSS_IDLE_FRAMES: (usize, usize) = (0, 4);
-        pub const BOSS_RUN_FRAMES: (usize, usize) = (5, 10);
-        // pub const BOSS_TRANSITION_TO_CHARGE_FRAMES: (usize, usize) = (11, 18);
-        pub const BOSS_CHARGE_FRAMES: (usize, usize) = (11, 14);
-        pub const BOSS_SMASH_FRAMES: (usize, usize) = (15, 18);
-        // TODO: Fix last frame FX -----vvv
-        pub const BOSS_FALLEN_ANGEL_FRAMES: (usize, usize) = (19, 26);
-        pub const BOSS_HIT_FRAMES: (usize, usize) = (27, 28);
-        pub const BOSS_DEAD_FRAMES: (usize, usize) = (29, 34);
+        pub const BOSS_ANIMATIONS: &str = "animations/boss.anim.ron";
+        pub const BOSS_ANIMATIONS_ATLAS: &str = "animations/boss.anim.ron#atlas";
     }
 }
 
+pub mod game_feel {
+    // -- Default Impact --
+    /// In real seconds
+    pub const HIT_STOP_DURATION: f32 = 0.06;
+    /// Screen shake amplitude per damage point
+    pub const SHAKE_PER_DAMAGE: f32 = 0.15;
+    pub const HIT_FLASH_DURATION: f32 = 0.08;
+    pub const SPARKS_COUNT: usize = 6;
+
+    pub const SHAKE_DURATION: f32 = 0.25;
+    /// The sprite colour multiplies the texture:
+    /// above 1. the sprite is saturated to white
+    pub const HIT_FLASH_BRIGHTNESS: f32 = 8.;
+
+    pub const SPARK_SIZE: f32 = 1.5;
+    pub const SPARK_SPEED: (f32, f32) = (40., 90.);
+    pub const SPARK_LIFETIME: f32 = 0.25;
+}
+
+pub mod soul_shift {
+    /// Speed of the soul travelling from the corpse to the new body
+    pub const SOUL_SPIRIT_SPEED: f32 = 250.;
+    pub const SOUL_SPIRIT_SIZE: f32 = 4.;
+    /// Once this close to the target, the soul enters its new body
+    pub const SOUL_SPIRIT_REACH: f32 = 2.;
+
+    /// `Time` relative speed during the soul shift
+    pub const SLOW_MOTION_SPEED: f32 = 0.3;
+    /// In real seconds (not affected by the slow motion)
+    pub const SLOW_MOTION_DURATION: f32 = 1.2;
+
+    /// Time for the spectator to jump from the grandstand to the fight plane
+    pub const JOIN_THE_FIGHT_DURATION: f32 = 0.6;
+    pub const JOIN_THE_FIGHT_JUMP_HEIGHT: f32 = 15.;
+}
+
+pub mod corpses {
+    /// Beyond this number of corpses, the oldest ones fade out
+    pub const MAX_CORPSES: usize = 10;
+    pub const CORPSE_FADE_OUT_DURATION: f32 = 2.;
+
+    /// Time during which a fresh corpse can be the target of a soul shift
+    pub const CORPSE_RECLAIM_WINDOW: f32 = 8.;
+}
+
 pub mod crowd {
-    pub const CROWD_SIZE: usize = 50;
-    pub const CROWD_SPAN: f32 = 1000.0;
+    /// Front row
     pub const CROWD_Y: f32 = -55.0;
     pub const CROWD_Z: f32 = 2.5;
+
+    // -- Palette --
+    // The tint multiplies the spritesheet colours:
+    // a light tint keeps the members readable
+    pub const CROWD_TINT_SATURATION: f32 = 0.6;
+    pub const CROWD_TINT_LIGHTNESS: f32 = 0.75;
+    /// Range of the animation speed of the members, for variety
+    pub const CROWD_PLAYBACK_SPEED: (f32, f32) = (0.8, 1.2);
+
+    // -- Grandstand --
+    /// Chance for a seat to be taken, from the front row to the back one
+    /// (default of the `config::GameplayConfig`)
+    pub const CROWD_ROW_DENSITY: [f32; 3] = [0.8, 0.6, 0.45];
+    /// Seats of each row, aisles included
+    /// (default of the `config::GameplayConfig`)
+    pub const CROWD_SEATS_BY_ROW: usize = 100;
+    /// Width of a seat
+    pub const CROWD_SEAT_WIDTH: f32 = 20.0;
+    /// Every x seats, an empty aisle
+    pub const CROWD_AISLE_EVERY: usize = 12;
+    /// How much the members can shift from the center of their seat
+    pub const CROWD_SEAT_JITTER: f32 = 6.0;
+
+    /// Each row is this much higher than the previous one
+    pub const ROW_STEP_Y: f32 = 9.0;
+    /// Each row is this much behind the previous one
+    /// (must stay above the `Hills Layer 03` at z = 2.)
+    pub const ROW_STEP_Z: f32 = 0.1;
+    /// Each row is this much smaller than the previous one
+    /// (also slows down the move with the background)
+    pub const ROW_STEP_SCALE: f32 = 0.1;
+    /// The front row moves this much against the fighters, for each of their steps
+    pub const CROWD_SCROLL: f32 = 0.36;
+
+    /// Draw benches under each row
+    pub const GRANDSTAND_STRUCTURE: bool = true;
+    pub const GRANDSTAND_STEP_HEIGHT: f32 = 4.0;
+    /// From the center of a member to their feet
+    pub const GRANDSTAND_STEP_OFFSET_Y: f32 = 14.0;
+}
+
+pub mod config {
+    /// The balancing values, see `config::GameplayConfig`
+    pub const GAMEPLAY_CONFIG: &str = "config/gameplay.config.ron";
+}
+
+pub mod debug {
+    use bevy::{input::keyboard::KeyCode, render::color::Color};
+
+    /// Without extension: `.json` or `.csv`
+    pub const COMBAT_LOG_EXPORT: &str = "combat_log";
+
+    // -- Overlay --
+    pub const OVERLAY_TOGGLE_KEY: KeyCode = KeyCode::F3;
+    pub const HURTBOX_COLOR: Color = Color::rgb(0.2, 0.9, 0.3);
+    pub const ATTACK_HITBOX_COLOR: Color = Color::rgb(0.45, 0.45, 0.5);
+    /// While its `ActiveEvents` are on
+    pub const ACTIVE_ATTACK_HITBOX_COLOR: Color = Color::rgb(1., 0.15, 0.1);
+    pub const RANGE_SENSOR_COLOR: Color = Color::rgb(0.95, 0.8, 0.2);
+    /// From the center of a character to the bottom of their label
+    pub const OVERLAY_LABEL_OFFSET_Y: f32 = 35.;
+
+    // -- Simulation Controls --
+    pub const PAUSE_KEY: KeyCode = KeyCode::F5;
+    /// Pauses, then plays a single tick
+    pub const STEP_KEY: KeyCode = KeyCode::F6;
+    /// 1x, 0.5x, 0.25x
+    pub const TIME_SCALE_KEY: KeyCode = KeyCode::F7;
+}
+
+pub mod training {
+    use bevy::{input::keyboard::KeyCode, render::color::Color};
+
+    pub const RESET_KEY: KeyCode = KeyCode::R;
+
+    // -- Damage Numbers --
+    pub const DAMAGE_NUMBER_FONT: &str = "fonts/dpcomic.ttf";
+    pub const DAMAGE_NUMBER_FONT_SIZE: f32 = 40.;
+    /// The font is rendered big then scaled down, to stay sharp
+    pub const DAMAGE_NUMBER_SCALE: f32 = 0.2;
+    pub const DAMAGE_NUMBER_COLOR: Color = Color::rgb(1., 0.85, 0.3);
+    /// In seconds
+    pub const DAMAGE_NUMBER_LIFETIME: f32 = 0.8;
+    /// In pixels per second
+    pub const DAMAGE_NUMBER_RISE: f32 = 20.;
+    /// From the center of the target
+    pub const DAMAGE_NUMBER_OFFSET_Y: f32 = 25.;
+}
+
+pub mod versus {
+    use bevy::render::color::Color;
+
+    /// First to win this many rounds wins the match
+    pub const ROUNDS_TO_WIN: u32 = 2;
+    /// From the center of the arena, at the start of each round
+    pub const START_DISTANCE: f32 = 40.;
+    /// Of the first body of the second player
+    pub const PLAYER_TWO_TINT: Color = Color::rgb(1., 0.6, 0.6);
+
+    // -- Tags and Banner --
+    pub const TAG_FONT: &str = "fonts/dpcomic.ttf";
+    pub const TAG_FONT_SIZE: f32 = 40.;
+    /// The font is rendered big then scaled down, to stay sharp
+    pub const TAG_SCALE: f32 = 0.2;
+    /// From the center of a fighter
+    pub const TAG_OFFSET_Y: f32 = 30.;
+    pub const BANNER_FONT_SIZE: f32 = 40.;
+}
+
+pub mod simulation {
+    use std::time::Duration;
+
+    /// Duration of a gameplay tick (60 per second)
+    pub const TICK: Duration = Duration::from_nanos(16_666_667);
+    /// Beyond, the simulation gives up catching up with the real time
+    pub const MAX_TICKS_PER_FRAME: u32 = 5;
+}
+
+pub mod replay {
+    /// Between two saves of the recording, in ticks: a crash loses the last ones only
+    pub const REPLAY_SAVE_INTERVAL: u64 = 5 * 60;
 }
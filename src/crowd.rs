@@ -1,138 +1,267 @@
 use crate::{
-    characters::animations::{AnimationIndices, AnimationTimer, CharacterState},
+    characters::{
+        aggression::DeadBody,
+        animations::{AnimationTimer, CharacterState, PlaybackSpeed, SpriteAnimations},
+        player::Player,
+    },
+    collisions::CROWD_GROUP,
+    config::{CrowdConfig, GameplayConfig},
     constants::{
-        character::player::*,
-        crowd::{CROWD_SIZE, CROWD_SPAN, CROWD_Y, CROWD_Z},
+        character::{player::*, FRAME_TIME},
+        crowd::*,
     },
+    simulation::{GameRng, GameplayApp, TickStage},
 };
-use bevy::{ecs::schedule::ShouldRun, prelude::*, utils::HashMap};
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
 use rand::Rng;
 
 pub struct CrowdPlugin;
 
 impl Plugin for CrowdPlugin {
     fn build(&self, app: &mut App) {
-        app.add_startup_system(setup)
-            .add_system(generate_crowd.with_run_criteria(texture_not_loaded))
-            .add_system(move_crowd_with_background)
-            .insert_resource(CharacterSpriteSheetLoaded(false));
+        app.add_startup_system(generate_crowd)
+            .add_gameplay_system(move_crowd_with_background)
+            .add_gameplay_system_to_stage(TickStage::First, reseat_crowd);
     }
 }
 
-#[derive(Debug, Deref, DerefMut, Resource)]
-struct CharacterSpritesheetImage(Handle<Image>);
-
-#[derive(Debug, Deref, DerefMut, Resource)]
-struct CharacterSpriteSheetLoaded(bool);
+/// The parent of the grandstand, seated according to this config
+#[derive(Debug, Component)]
+pub struct Crowd(pub CrowdConfig);
 
 #[derive(Debug, Component)]
 pub struct CrowdMember;
 
-fn texture_not_loaded(character_spritehseet_loaded: Res<CharacterSpriteSheetLoaded>) -> ShouldRun {
-    if **character_spritehseet_loaded {
-        ShouldRun::No
-    } else {
-        ShouldRun::Yes
+/// Colour variation of a crowd member.
+///
+/// All the crowd shares the same spritesheet,
+/// their sprite is tinted with this colour instead.
+/// The second player of a versus wears one too.
+///
+/// The flashes and highlights give it back to the sprite once over.
+#[derive(Debug, Clone, Copy, Component, Deref, DerefMut)]
+pub struct CrowdTint(pub Color);
+
+/// Row of the grandstand, 0 being the front row.
+#[derive(Debug, Clone, Copy, Component, Deref, DerefMut)]
+pub struct GrandstandRow(pub usize);
+
+impl GrandstandRow {
+    pub fn y(&self) -> f32 {
+        CROWD_Y + self.0 as f32 * ROW_STEP_Y
+    }
+
+    pub fn z(&self) -> f32 {
+        CROWD_Z - self.0 as f32 * ROW_STEP_Z
     }
-}
 
-fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
-    let image = asset_server.load("textures/character/character_spritesheet_v2.png");
-    commands.insert_resource(CharacterSpritesheetImage(image));
+    pub fn scale(&self) -> f32 {
+        1. - self.0 as f32 * ROW_STEP_SCALE
+    }
 }
 
+/// Bench under a row of the grandstand
+#[derive(Debug, Component)]
+pub struct GrandstandStructure;
+
+/// The grandstand moves against the fighters, as the camera follows them.
+///
+/// The further the row is, the slower it moves.
 fn move_crowd_with_background(
-    mut query: Query<&mut Transform, With<CrowdMember>>,
-    keyboard_input: Res<Input<KeyCode>>,
+    mut query: Query<
+        (&mut Transform, &GrandstandRow),
+        Or<(With<CrowdMember>, With<GrandstandStructure>)>,
+    >,
+    fighter_query: Query<&Velocity, (With<Player>, Without<CrowdMember>, Without<DeadBody>)>,
     time: Res<Time>,
 ) {
-    for mut transform in query.iter_mut() {
-        let right = keyboard_input.pressed(KeyCode::D) || keyboard_input.pressed(KeyCode::Right);
-        let left = keyboard_input.pressed(KeyCode::Q)
-            || keyboard_input.pressed(KeyCode::A)
-            || keyboard_input.pressed(KeyCode::Left);
-
-        let dir = right as i8 - left as i8;
-        const SPEED: f32 = 18.0;
+    let velocities: Vec<f32> = fighter_query
+        .iter()
+        .map(|velocity| velocity.linvel.x)
+        .collect();
+    if velocities.is_empty() {
+        return;
+    }
+    // The camera follows the middle of the fighters
+    let velocity = velocities.iter().sum::<f32>() / velocities.len() as f32;
 
-        transform.translation.x += -dir as f32 * SPEED * time.delta_seconds();
+    for (mut transform, row) in query.iter_mut() {
+        transform.translation.x -= velocity * CROWD_SCROLL * row.scale() * time.delta_seconds();
     }
 }
 
+/// Seats the crowd of the `GameplayConfig`.
 fn generate_crowd(
-    mut character_spritehseet_loaded: ResMut<CharacterSpriteSheetLoaded>,
     mut commands: Commands,
-    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
-    mut assets: ResMut<Assets<Image>>,
-    character_spritesheet_image: Res<CharacterSpritesheetImage>,
+    asset_server: Res<AssetServer>,
+    config: Res<GameplayConfig>,
+    mut rand: ResMut<GameRng>,
 ) {
-    if let Some(image_handle) = assets.get(&**character_spritesheet_image) {
-        let mut animation_indices = AnimationIndices(HashMap::new());
-        animation_indices.insert(CharacterState::Idle, PLAYER_IDLE_FRAMES);
-        animation_indices.insert(CharacterState::Run, PLAYER_RUN_FRAMES);
-        animation_indices.insert(
-            CharacterState::TransitionToCharge,
-            PLAYER_TRANSITION_TO_CHARGE_FRAMES,
+    let crowd = commands
+        .spawn((
+            Name::new("Crowd"),
+            Crowd(config.crowd.clone()),
+            GlobalTransform::default(),
+            Transform::default(),
+            ComputedVisibility::default(),
+            Visibility::default(),
+        ))
+        .id();
+
+    fill_grandstand(
+        &mut commands,
+        crowd,
+        &config.crowd,
+        &asset_server,
+        &mut rand,
+    );
+}
+
+/// A new crowd replaces the spectators when the size of the crowd is tuned.
+///
+/// The possessed spectators and the corpses stay.
+fn reseat_crowd(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    config: Res<GameplayConfig>,
+    mut rand: ResMut<GameRng>,
+    mut crowd_query: Query<(Entity, &mut Crowd)>,
+    seated_query: Query<Entity, Or<(With<CrowdMember>, With<GrandstandStructure>)>>,
+) {
+    if !config.is_changed() {
+        return;
+    }
+
+    for (crowd, mut seated) in &mut crowd_query {
+        if seated.0 == config.crowd {
+            continue;
+        }
+
+        for entity in &seated_query {
+            commands.entity(entity).despawn_recursive();
+        }
+        fill_grandstand(
+            &mut commands,
+            crowd,
+            &config.crowd,
+            &asset_server,
+            &mut rand,
         );
-        animation_indices.insert(CharacterState::Charge, PLAYER_CHARGE_FRAMES);
-        animation_indices.insert(CharacterState::Attack, PLAYER_FULL_ATTACK_FRAMES);
-        animation_indices.insert(CharacterState::SecondAttack, PLAYER_SECOND_ATTACK_FRAMES);
-        animation_indices.insert(CharacterState::Hit, PLAYER_HIT_FRAMES);
-        animation_indices.insert(CharacterState::Dead, PLAYER_DEAD_FRAMES);
-
-        **character_spritehseet_loaded = true;
-        let image_handle = image_handle.clone();
-
-        let mut rand = rand::thread_rng();
-        let crowd_member_spacing = CROWD_SPAN * 2.0 / CROWD_SIZE as f32;
-        let mut current_crowd_member_x = -CROWD_SPAN;
-
-        let parent = commands
-            .spawn((
-                Name::new("Crowd"),
-                GlobalTransform::default(),
-                Transform::default(),
-                ComputedVisibility::default(),
-                Visibility::default(),
-            ))
-            .id();
-
-        for _ in 0..CROWD_SIZE {
-            let image = image_handle.clone();
-            let mut image_dynamic = image.try_into_dynamic().unwrap();
-            image_dynamic = image_dynamic.huerotate(rand.gen_range(0..360));
-            // .brighten(-50);
-
-            let texture_handle = assets.add(Image::from_dynamic(image_dynamic, true));
-            let texture_atlas =
-                TextureAtlas::from_grid(texture_handle, Vec2::new(200., 200.), 35, 1, None, None);
-            let texture_atlas_handle = texture_atlases.add(texture_atlas);
-
-            let texture_atlas_sprite = TextureAtlasSprite::new(0);
-
-            commands.entity(parent).with_children(|parent| {
+        seated.0 = config.crowd.clone();
+    }
+}
+
+/// Fill the grandstand, row by row.
+///
+/// Each of the `seats_by_row` seats is taken according to the density of its row,
+/// leaving an aisle every `CROWD_AISLE_EVERY` seats.
+fn fill_grandstand(
+    commands: &mut Commands,
+    crowd: Entity,
+    config: &CrowdConfig,
+    asset_server: &AssetServer,
+    rand: &mut GameRng,
+) {
+    // One atlas for the whole crowd
+    let texture_atlas_handle: Handle<TextureAtlas> = asset_server.load(PLAYER_ANIMATIONS_ATLAS);
+    let animations_handle: Handle<SpriteAnimations> = asset_server.load(PLAYER_ANIMATIONS);
+
+    let span = config.seats_by_row as f32 * CROWD_SEAT_WIDTH / 2.0;
+
+    for (row_index, row_density) in config.row_density.iter().enumerate() {
+        let row = GrandstandRow(row_index);
+
+        commands.entity(crowd).with_children(|parent| {
+            if GRANDSTAND_STRUCTURE {
                 parent.spawn((
-                    SpriteSheetBundle {
-                        texture_atlas: texture_atlas_handle,
-                        sprite: texture_atlas_sprite,
-                        transform: Transform::from_translation(Vec3::new(
-                            current_crowd_member_x + rand.gen_range(-10.0..=10.0),
-                            CROWD_Y,
-                            CROWD_Z,
-                        )),
+                    SpriteBundle {
+                        sprite: Sprite {
+                            color: Color::rgb(0.25, 0.2, 0.18),
+                            custom_size: Some(Vec2::new(
+                                span * 2.0 + CROWD_SEAT_WIDTH,
+                                GRANDSTAND_STEP_HEIGHT,
+                            )),
+                            ..default()
+                        },
+                        transform: Transform::from_xyz(
+                            0.,
+                            row.y() - GRANDSTAND_STEP_OFFSET_Y * row.scale(),
+                            // Right behind the members of the row
+                            row.z() - ROW_STEP_Z / 2.,
+                        ),
                         ..default()
                     },
-                    CrowdMember,
-                    CharacterState::Idle,
-                    AnimationTimer(Timer::from_seconds(
-                        0.1 + rand.gen_range(-0.02..0.02),
-                        TimerMode::Repeating,
-                    )),
-                    animation_indices.clone(),
+                    GrandstandStructure,
+                    row,
+                    Name::new(format!("Grandstand Row n°{}", row_index)),
                 ));
-            });
+            }
 
-            current_crowd_member_x += crowd_member_spacing;
-        }
+            for seat in 0..config.seats_by_row {
+                // Aisle
+                if seat % CROWD_AISLE_EVERY == CROWD_AISLE_EVERY - 1 {
+                    continue;
+                }
+                // Empty seat
+                if !rand.gen_bool(*row_density as f64) {
+                    continue;
+                }
+
+                let seat_x = -span + (seat as f32 + 0.5) * CROWD_SEAT_WIDTH;
+
+                parent.spawn(spectator(
+                    row,
+                    seat_x,
+                    texture_atlas_handle.clone(),
+                    animations_handle.clone(),
+                    rand,
+                ));
+            }
+        });
     }
 }
+
+/// Someone of the `row`, sitting around `seat_x`
+pub fn spectator(
+    row: GrandstandRow,
+    seat_x: f32,
+    texture_atlas: Handle<TextureAtlas>,
+    animations: Handle<SpriteAnimations>,
+    rand: &mut GameRng,
+) -> impl Bundle {
+    let tint = CrowdTint(Color::hsl(
+        rand.gen_range(0.0..360.0),
+        CROWD_TINT_SATURATION,
+        CROWD_TINT_LIGHTNESS,
+    ));
+
+    let mut texture_atlas_sprite = TextureAtlasSprite::new(0);
+    texture_atlas_sprite.color = *tint;
+
+    (
+        SpriteSheetBundle {
+            texture_atlas,
+            sprite: texture_atlas_sprite,
+            transform: Transform {
+                translation: Vec3::new(
+                    seat_x + rand.gen_range(-CROWD_SEAT_JITTER..=CROWD_SEAT_JITTER),
+                    row.y(),
+                    row.z(),
+                ),
+                scale: Vec3::splat(row.scale()),
+                ..default()
+            },
+            ..default()
+        },
+        CrowdMember,
+        // Never part of the fight, until possessed
+        CollisionGroups::new(CROWD_GROUP, Group::NONE),
+        tint,
+        row,
+        CharacterState::Idle,
+        AnimationTimer(Timer::from_seconds(FRAME_TIME, TimerMode::Repeating)),
+        PlaybackSpeed(rand.gen_range(CROWD_PLAYBACK_SPEED.0..CROWD_PLAYBACK_SPEED.1)),
+        animations,
+    )
+}
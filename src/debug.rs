@@ -1,7 +1,21 @@
 use bevy::prelude::*;
-use bevy_inspector_egui::{RegisterInspectable, WorldInspectorPlugin};
+use bevy_inspector_egui::{
+    bevy_egui::EguiContext, egui, RegisterInspectable, WorldInspectorPlugin,
+};
+use bevy_rapier2d::prelude::*;
 
-use crate::characters::animations::CharacterState;
+use crate::{
+    characters::{
+        aggression::{AttackCooldown, AttackHitbox, ComboPenalty, DeadBody, Hp, Invulnerable},
+        animations::CharacterState,
+        movement::CharacterHitbox,
+        npcs::boss::{aggression::BossSensor, Boss},
+        player::Player,
+    },
+    combat_log::{CombatEvent, CombatLog},
+    constants::debug::*,
+    simulation::{SimulationControl, SimulationTick, TimeScale},
+};
 
 pub struct DebugPlugin;
 
@@ -11,8 +25,232 @@ impl Plugin for DebugPlugin {
         if cfg!(debug_assertions) {
             app.add_plugin(WorldInspectorPlugin::new())
                 .register_inspectable::<CharacterState>()
+                // -- Overlay --
+                .add_plugin(RapierDebugRenderPlugin::default().disabled())
+                .add_system(toggle_overlay)
+                .add_system(color_colliders)
+                .add_system(character_overlay)
+                // -- Simulation Controls --
+                .add_system(simulation_keys)
+                .add_system(simulation_panel)
                 // UI
+                .add_system(combat_log_panel)
                 ;
         }
     }
 }
+
+/// The entries of the `CombatLog`, the most recent at the bottom.
+///
+/// The state changes are hidden by default: there is one for every step.
+fn combat_log_panel(
+    mut egui_context: ResMut<EguiContext>,
+    combat_log: Res<CombatLog>,
+    mut show_state_changes: Local<bool>,
+) {
+    egui::Window::new("Combat Log")
+        .collapsible(true)
+        .show(egui_context.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut show_state_changes, "State changes");
+
+                for extension in ["json", "csv"] {
+                    let path = format!("{}.{}", COMBAT_LOG_EXPORT, extension);
+                    if ui.button(format!("Export {}", path)).clicked() {
+                        match combat_log.export(&path) {
+                            Ok(()) => info!("Combat log exported in {}", path),
+                            Err(e) => error!("Can't export the combat log in {}: {}", path, e),
+                        }
+                    }
+                }
+            });
+            ui.separator();
+
+            egui::ScrollArea::vertical()
+                .stick_to_bottom(true)
+                .show(ui, |ui| {
+                    for entry in combat_log.entries.iter().filter(|entry| {
+                        *show_state_changes
+                            || !matches!(entry.event, CombatEvent::StateChange { .. })
+                    }) {
+                        ui.label(entry.to_string());
+                    }
+                });
+        });
+}
+
+/// Pause, step and slow down the ticks, see `SimulationControl`
+pub fn simulation_keys(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut control: ResMut<SimulationControl>,
+) {
+    if keyboard_input.just_pressed(PAUSE_KEY) {
+        control.paused = !control.paused;
+    }
+    if keyboard_input.just_pressed(STEP_KEY) {
+        control.step();
+    }
+    if keyboard_input.just_pressed(TIME_SCALE_KEY) {
+        control.time_scale = control.time_scale.next();
+    }
+}
+
+/// The same controls, with the current tick
+fn simulation_panel(
+    mut egui_context: ResMut<EguiContext>,
+    tick: Res<SimulationTick>,
+    mut control: ResMut<SimulationControl>,
+) {
+    egui::Window::new("Simulation")
+        .collapsible(true)
+        .show(egui_context.ctx_mut(), |ui| {
+            ui.label(format!("Tick {}", **tick));
+            ui.horizontal(|ui| {
+                let pause = if control.paused { "Resume" } else { "Pause" };
+                if ui.button(format!("{} ({:?})", pause, PAUSE_KEY)).clicked() {
+                    control.paused = !control.paused;
+                }
+                if ui.button(format!("Step ({:?})", STEP_KEY)).clicked() {
+                    control.step();
+                }
+            });
+            ui.horizontal(|ui| {
+                for time_scale in TimeScale::ALL {
+                    ui.radio_value(&mut control.time_scale, time_scale, time_scale.to_string());
+                }
+            });
+        });
+}
+
+/// Shows/Hides the colliders and the characters' labels.
+pub fn toggle_overlay(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut debug_render_context: ResMut<DebugRenderContext>,
+) {
+    if keyboard_input.just_pressed(OVERLAY_TOGGLE_KEY) {
+        debug_render_context.enabled = !debug_render_context.enabled;
+    }
+}
+
+/// Tells the hurtboxes, the attack hitboxes (lit up while active)
+/// and the range sensor of the boss apart.
+fn color_colliders(
+    mut commands: Commands,
+    hurtbox_query: Query<Entity, (With<CharacterHitbox>, Without<ColliderDebugColor>)>,
+    range_sensor_query: Query<Entity, (With<BossSensor>, Without<ColliderDebugColor>)>,
+    attack_hitbox_query: Query<
+        (Entity, Option<&ActiveEvents>, Option<&ColliderDebugColor>),
+        With<AttackHitbox>,
+    >,
+) {
+    for hurtbox in &hurtbox_query {
+        commands
+            .entity(hurtbox)
+            .insert(ColliderDebugColor(HURTBOX_COLOR));
+    }
+
+    for range_sensor in &range_sensor_query {
+        commands
+            .entity(range_sensor)
+            .insert(ColliderDebugColor(RANGE_SENSOR_COLOR));
+    }
+
+    for (attack_hitbox, active_events, debug_color) in &attack_hitbox_query {
+        let color = if active_events.is_some() {
+            ACTIVE_ATTACK_HITBOX_COLOR
+        } else {
+            ATTACK_HITBOX_COLOR
+        };
+
+        if debug_color.map(|debug_color| debug_color.0) != Some(color) {
+            commands
+                .entity(attack_hitbox)
+                .insert(ColliderDebugColor(color));
+        }
+    }
+}
+
+/// Above each fighter: their state, frame, hp, cooldowns and invulnerability.
+///
+/// The boss's smash cooldown is held by its range sensor, a child.
+fn character_overlay(
+    mut egui_context: ResMut<EguiContext>,
+    debug_render_context: Res<DebugRenderContext>,
+    camera_query: Query<(&Camera, &GlobalTransform)>,
+    character_query: Query<
+        (
+            Entity,
+            &Name,
+            &GlobalTransform,
+            &CharacterState,
+            &TextureAtlasSprite,
+            &Hp,
+            Option<&Invulnerable>,
+            Option<&ComboPenalty>,
+            Option<&Children>,
+        ),
+        (Or<(With<Player>, With<Boss>)>, Without<DeadBody>),
+    >,
+    cooldown_query: Query<&AttackCooldown>,
+) {
+    if !debug_render_context.enabled {
+        return;
+    }
+
+    let (camera, camera_transform) = match camera_query.get_single() {
+        Ok(camera) => camera,
+        Err(_) => return,
+    };
+    let viewport_height = match camera.logical_viewport_size() {
+        Some(size) => size.y,
+        None => return,
+    };
+
+    for (character, name, transform, state, sprite, hp, invulnerable, combo_penalty, children) in
+        &character_query
+    {
+        let label_position = transform.translation() + Vec3::Y * OVERLAY_LABEL_OFFSET_Y;
+        // From the bottom left corner of the viewport, to the top left one of the window
+        let position = match camera.world_to_viewport(camera_transform, label_position) {
+            Some(position) => egui::pos2(position.x, viewport_height - position.y),
+            None => continue,
+        };
+
+        let mut lines = vec![
+            name.to_string(),
+            format!("{:?} - frame {}", state, sprite.index),
+            format!("Hp {}/{}", hp.current, hp.max),
+        ];
+
+        let cooldown = cooldown_query.get(character).ok().or_else(|| {
+            children
+                .into_iter()
+                .flatten()
+                .find_map(|child| cooldown_query.get(*child).ok())
+        });
+        if let Some(cooldown) = cooldown {
+            lines.push(format!("Cooldown {:.2}s", cooldown.remaining_secs()));
+        }
+        if let Some(combo_penalty) = combo_penalty {
+            lines.push(format!(
+                "Combo penalty {:.2}s",
+                combo_penalty.remaining_secs()
+            ));
+        }
+        if let Some(invulnerable) = invulnerable {
+            lines.push(format!(
+                "Invulnerable {:.2}s",
+                invulnerable.remaining_secs()
+            ));
+        }
+
+        egui::Area::new(egui::Id::new(character))
+            .fixed_pos(position)
+            .pivot(egui::Align2::CENTER_BOTTOM)
+            .interactable(false)
+            .order(egui::Order::Background)
+            .show(egui_context.ctx_mut(), |ui| {
+                ui.label(egui::RichText::new(lines.join("\n")).monospace().small());
+            });
+    }
+}
@@ -0,0 +1,301 @@
+//! Combat feedback
+//!
+//! Every impact freezes the fight for a few frames (hit-stop),
+//! shakes the camera, flashes the hurt sprite and throws sparks.
+
+use bevy::{ecs::schedule::ShouldRun, prelude::*};
+use bevy_rapier2d::prelude::RapierConfiguration;
+use rand::Rng;
+
+use crate::{
+    camera::camera_follow,
+    characters::aggression::{AttackHitbox, DamageDealtEvent},
+    constants::{character::CHAR_Z, game_feel::*},
+    crowd::CrowdTint,
+    simulation::GameplayApp,
+};
+
+pub struct GameFeelPlugin;
+
+impl Plugin for GameFeelPlugin {
+    #[rustfmt::skip]
+    fn build(&self, app: &mut App) {
+        app .init_resource::<ScreenShake>()
+            // The hit-stop freezes the simulation: it is part of it
+            .add_gameplay_system(impact_feedback.label("Impact Feedback").after("Damage Hit"))
+            .add_gameplay_system(hit_stop.after("Impact Feedback"))
+            .add_system(screen_shake.after(camera_follow))
+            .add_system(hit_flash)
+            .add_system(animate_sparks)
+            ;
+    }
+}
+
+/// How an attack feels when it lands.
+///
+/// Put it next to an `AttackHitbox`,
+/// the default is used for hitboxes without it.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct ImpactFeel {
+    /// Freeze duration, in real seconds
+    pub hit_stop: f32,
+    /// Screen shake amplitude per damage point
+    pub shake_per_damage: f32,
+    pub flash: f32,
+    pub sparks: usize,
+}
+
+impl Default for ImpactFeel {
+    fn default() -> Self {
+        ImpactFeel {
+            hit_stop: HIT_STOP_DURATION,
+            shake_per_damage: SHAKE_PER_DAMAGE,
+            flash: HIT_FLASH_DURATION,
+            sparks: SPARKS_COUNT,
+        }
+    }
+}
+
+/// While this resource exists, animations and physics are frozen.
+///
+/// The timer is ticked with the real time.
+#[derive(Resource, Deref, DerefMut)]
+pub struct HitStop(pub Timer);
+
+#[derive(Resource)]
+pub struct ScreenShake {
+    pub intensity: f32,
+    pub timer: Timer,
+    /// Offset applied to the camera the last frame
+    offset: Vec2,
+}
+
+impl Default for ScreenShake {
+    fn default() -> Self {
+        let mut timer = Timer::from_seconds(SHAKE_DURATION, TimerMode::Once);
+        // Not shaking at startup
+        timer.tick(timer.duration());
+
+        ScreenShake {
+            intensity: 0.,
+            timer,
+            offset: Vec2::ZERO,
+        }
+    }
+}
+
+/// The sprite is saturated to white.
+#[derive(Component, Deref, DerefMut)]
+pub struct HitFlash(pub Timer);
+
+#[derive(Component)]
+pub struct Spark {
+    pub velocity: Vec2,
+    pub lifetime: Timer,
+}
+
+/// Run Criteria
+///
+/// Frozen during the hit-stop.
+pub fn not_in_hit_stop(hit_stop: Option<Res<HitStop>>) -> ShouldRun {
+    if hit_stop.is_some() {
+        ShouldRun::No
+    } else {
+        ShouldRun::Yes
+    }
+}
+
+/// Trigger all the feedbacks of the landed attacks,
+/// tuned by their `ImpactFeel`.
+///
+/// Only the damage actually dealt is felt:
+/// a hit on an invulnerable target, or fully absorbed, has no impact.
+fn impact_feedback(
+    mut commands: Commands,
+    mut damage_dealt_event: EventReader<DamageDealtEvent>,
+
+    attack_hitbox_query: Query<(Option<&ImpactFeel>, &GlobalTransform), With<AttackHitbox>>,
+    target_query: Query<&GlobalTransform>,
+    hit_stop: Option<Res<HitStop>>,
+    mut screen_shake: ResMut<ScreenShake>,
+) {
+    let mut longest_hit_stop = hit_stop.map_or(0., |hit_stop| {
+        hit_stop.duration().as_secs_f32() - hit_stop.elapsed_secs()
+    });
+    let mut new_hit_stop = false;
+
+    for DamageDealtEvent {
+        attack_hitbox,
+        target,
+        damage,
+    } in damage_dealt_event.iter()
+    {
+        match (
+            attack_hitbox_query.get(*attack_hitbox),
+            target_query.get(*target),
+        ) {
+            (Err(e), _) | (_, Err(e)) => warn!("No impact for this hit: {:?}", e),
+            (Ok((impact_feel, hitbox_transform)), Ok(target_transform)) => {
+                let impact_feel = impact_feel.copied().unwrap_or_default();
+
+                // -- Hit-Stop --
+                if impact_feel.hit_stop > longest_hit_stop {
+                    longest_hit_stop = impact_feel.hit_stop;
+                    new_hit_stop = true;
+                }
+
+                // -- Screen Shake --
+                let intensity = impact_feel.shake_per_damage * *damage as f32;
+                if intensity >= screen_shake.intensity * screen_shake.timer.percent_left() {
+                    screen_shake.intensity = intensity;
+                    screen_shake.timer.reset();
+                }
+
+                // -- Flash --
+                commands
+                    .entity(*target)
+                    .insert(HitFlash(Timer::from_seconds(
+                        impact_feel.flash,
+                        TimerMode::Once,
+                    )));
+
+                // -- Sparks --
+                let contact_point = hitbox_transform
+                    .translation()
+                    .lerp(target_transform.translation(), 0.5);
+
+                let mut rand = rand::thread_rng();
+                for _ in 0..impact_feel.sparks {
+                    let direction = Vec2::from_angle(rand.gen_range(0.0..std::f32::consts::TAU));
+                    let speed = rand.gen_range(SPARK_SPEED.0..SPARK_SPEED.1);
+
+                    commands.spawn((
+                        SpriteBundle {
+                            sprite: Sprite {
+                                color: Color::rgb(1., 0.9, 0.5),
+                                custom_size: Some(Vec2::splat(SPARK_SIZE)),
+                                ..default()
+                            },
+                            transform: Transform::from_xyz(
+                                contact_point.x,
+                                contact_point.y,
+                                CHAR_Z + 2.,
+                            ),
+                            ..default()
+                        },
+                        Spark {
+                            velocity: direction * speed,
+                            lifetime: Timer::from_seconds(SPARK_LIFETIME, TimerMode::Once),
+                        },
+                        Name::new("Spark"),
+                    ));
+                }
+            }
+        }
+    }
+
+    if new_hit_stop {
+        commands.insert_resource(HitStop(Timer::from_seconds(
+            longest_hit_stop,
+            TimerMode::Once,
+        )));
+    }
+}
+
+/// Freeze the physics while the hit-stop lasts.
+///
+/// The animations are frozen by the `not_in_hit_stop` run criteria.
+fn hit_stop(
+    mut commands: Commands,
+    time: Res<Time>,
+    hit_stop: Option<ResMut<HitStop>>,
+    mut rapier_config: ResMut<RapierConfiguration>,
+) {
+    if let Some(mut hit_stop) = hit_stop {
+        rapier_config.physics_pipeline_active = false;
+        hit_stop.tick(time.raw_delta());
+
+        if hit_stop.finished() {
+            rapier_config.physics_pipeline_active = true;
+            commands.remove_resource::<HitStop>();
+        }
+    }
+}
+
+/// Shake around the position given by `camera_follow`,
+/// weaker and weaker.
+fn screen_shake(
+    time: Res<Time>,
+    mut screen_shake: ResMut<ScreenShake>,
+    mut camera_query: Query<&mut Transform, With<Camera>>,
+) {
+    let mut camera_transform = match camera_query.get_single_mut() {
+        Ok(camera_transform) => camera_transform,
+        // e.g. headless
+        Err(_) => return,
+    };
+
+    // Remove the last shake
+    camera_transform.translation -= screen_shake.offset.extend(0.);
+
+    screen_shake.timer.tick(time.raw_delta());
+    screen_shake.offset = if screen_shake.timer.finished() {
+        Vec2::ZERO
+    } else {
+        let mut rand = rand::thread_rng();
+        let amplitude = screen_shake.intensity * screen_shake.timer.percent_left();
+
+        Vec2::new(
+            rand.gen_range(-1.0..=1.0) * amplitude,
+            rand.gen_range(-1.0..=1.0) * amplitude,
+        )
+    };
+
+    camera_transform.translation += screen_shake.offset.extend(0.);
+}
+
+/// Saturate the sprite to white, then back to its own colours.
+fn hit_flash(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut flashing_query: Query<(
+        Entity,
+        &mut HitFlash,
+        &mut TextureAtlasSprite,
+        Option<&CrowdTint>,
+    )>,
+) {
+    for (character, mut hit_flash, mut sprite, tint) in flashing_query.iter_mut() {
+        // The flash must be seen during the hit-stop
+        hit_flash.tick(time.raw_delta());
+
+        if hit_flash.finished() {
+            sprite.color = tint.map_or(Color::WHITE, |tint| **tint);
+            commands.entity(character).remove::<HitFlash>();
+        } else {
+            sprite.color = Color::rgb(
+                HIT_FLASH_BRIGHTNESS,
+                HIT_FLASH_BRIGHTNESS,
+                HIT_FLASH_BRIGHTNESS,
+            );
+        }
+    }
+}
+
+fn animate_sparks(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut spark_query: Query<(Entity, &mut Spark, &mut Transform, &mut Sprite)>,
+) {
+    for (spark, mut spark_info, mut transform, mut sprite) in spark_query.iter_mut() {
+        spark_info.lifetime.tick(time.raw_delta());
+
+        if spark_info.lifetime.finished() {
+            commands.entity(spark).despawn();
+        } else {
+            transform.translation += (spark_info.velocity * time.raw_delta_seconds()).extend(0.);
+            let alpha = spark_info.lifetime.percent_left();
+            sprite.color.set_a(alpha);
+        }
+    }
+}
@@ -0,0 +1,18 @@
+#![allow(clippy::type_complexity, clippy::too_many_arguments)]
+
+pub mod camera;
+pub mod characters;
+pub mod collisions;
+pub mod combat_log;
+pub mod config;
+pub mod constants;
+pub mod crowd;
+pub mod debug;
+pub mod game_feel;
+pub mod locations;
+pub mod replay;
+pub mod simulation;
+pub mod soul_shift;
+pub mod training;
+pub mod ui;
+pub mod versus;
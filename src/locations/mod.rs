@@ -1,8 +1,11 @@
 use bevy::prelude::*;
 use bevy_parallax::{LayerData, ParallaxMoveEvent, ParallaxResource};
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Eq, PartialEq, Debug, Hash)]
-enum Location {
+/// The arena of the fight
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Default, Serialize, Deserialize)]
+pub enum Location {
+    #[default]
     Desert,
 }
 
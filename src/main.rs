@@ -1,19 +1,12 @@
-#![allow(clippy::type_complexity)]
-
-pub mod camera;
-pub mod characters;
-pub mod collisions;
-pub mod constants;
-mod crowd;
-mod debug;
-mod locations;
-mod soul_shift;
-mod ui;
-
 use bevy::prelude::*;
 use bevy_parallax::{ParallaxCameraComponent, ParallaxPlugin};
 use bevy_rapier2d::prelude::*;
 
+use grandstand_gauntlet::{
+    characters, combat_log, config, constants, crowd, debug, game_feel, locations, replay,
+    simulation, soul_shift, training, ui, versus,
+};
+
 use characters::CharacterPlugin;
 use constants::{CLEAR, TILE_SIZE};
 use debug::DebugPlugin;
@@ -21,6 +14,26 @@ use locations::LocationsPlugin;
 
 #[rustfmt::skip]
 fn main() {
+    let replay_plugin = match replay::ReplayPlugin::from_args() {
+        Ok(replay_plugin) => replay_plugin,
+        Err(e) => {
+            eprintln!("Can't record or replay the fight: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let combat_log_plugin = match combat_log::CombatLogPlugin::from_args() {
+        Ok(combat_log_plugin) => combat_log_plugin,
+        Err(e) => {
+            eprintln!("Can't export the combat log: {}", e);
+            std::process::exit(1);
+        }
+    };
+    // A replay is played in the mode it was recorded in
+    let mode = match &replay_plugin.replay {
+        Some(replay) => replay.mode.clone(),
+        None => replay::GameMode::from_args(),
+    };
+
     App::new()
         .insert_resource(ClearColor(CLEAR))
         .insert_resource(Msaa { samples: 1 })
@@ -39,15 +52,22 @@ fn main() {
                     },
                     ..default()
                 })
-                .set(ImagePlugin::default_nearest()),
+                .set(ImagePlugin::default_nearest())
+                // Hot reload of the gameplay config, see `config`
+                .set(AssetPlugin {
+                    watch_for_changes: cfg!(not(target_arch = "wasm32")),
+                    ..default()
+                }),
+        )
+        // The physics steps with the gameplay, see `simulation::GameplayStage`
+        .add_plugin(
+            RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(TILE_SIZE)
+                .with_default_system_setup(false),
         )
-        // .add_plugin(RapierDebugRenderPlugin {
-        //     mode: DebugRenderMode::all(),
-        //     ..default()
-        // })
-        .add_plugin(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(
-            TILE_SIZE,
-        ))
+        .add_plugin(simulation::SimulationPlugin)
+        .add_plugin(replay_plugin)
+        .add_plugin(combat_log_plugin)
+        .add_plugin(config::ConfigPlugin)
         .add_plugin(ParallaxPlugin)
         .add_plugin(LocationsPlugin)
         .add_plugin(CharacterPlugin)
@@ -55,6 +75,9 @@ fn main() {
         .add_plugin(crowd::CrowdPlugin)
         .add_plugin(soul_shift::SoulShiftPlugin)
         .add_plugin(ui::UiPlugin)
+        .add_plugin(game_feel::GameFeelPlugin)
+        .add_plugin(training::TrainingPlugin { training: mode.training() })
+        .add_plugin(versus::VersusPlugin { versus: mode.versus() })
         .add_startup_system(spawn_camera)
         .run();
 }
@@ -0,0 +1,423 @@
+//! Input Recording and Replay
+//!
+//! Every fight is recorded: the `Seed`, the arena, the boss, the `GameMode`,
+//! the `GameplayConfig` in use and the inputs of each tick.
+//! Played back through the same systems, a replay reproduces the exact fight.
+//!
+//! The recording is saved every few seconds, and on exit.
+//!
+//! ```text
+//! grandstand-gauntlet --record fight.replay.ron
+//! grandstand-gauntlet --replay fight.replay.ron
+//! ```
+
+use std::{hash::Hash, path::PathBuf};
+
+use bevy::{app::AppExit, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::GameplayConfig,
+    constants::{character::boss::BOSS_ID, replay::REPLAY_SAVE_INTERVAL},
+    locations::Location,
+    simulation::{GameplayApp, Seed, SimulationTick, TickStage},
+    training::Training,
+    versus::Versus,
+};
+
+/// Records the fight, and plays the replay if any.
+#[derive(Default)]
+pub struct ReplayPlugin {
+    pub replay: Option<Replay>,
+    /// Where the recording is saved
+    pub record_to: Option<PathBuf>,
+}
+
+impl ReplayPlugin {
+    /// `--replay <file>` and `--record <file>`, the other arguments are ignored.
+    ///
+    /// Fails if the replay can't be read: the fight wouldn't be the asked one.
+    pub fn from_args() -> Result<Self, ReplayError> {
+        ReplayPlugin::parse_args(std::env::args().skip(1))
+    }
+
+    /// See `from_args`
+    pub fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Self, ReplayError> {
+        let mut plugin = ReplayPlugin::default();
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            if arg != "--replay" && arg != "--record" {
+                continue;
+            }
+            let path = args.next().ok_or(ReplayError::MissingPath(arg.clone()))?;
+            if arg == "--replay" {
+                plugin.replay = Some(Replay::load(&path)?);
+            } else {
+                plugin.record_to = Some(path.into());
+            }
+        }
+
+        Ok(plugin)
+    }
+}
+
+impl Plugin for ReplayPlugin {
+    #[rustfmt::skip]
+    fn build(&self, app: &mut App) {
+        if let Some(replay) = &self.replay {
+            info!("Replay of {} ticks", replay.duration());
+            app.insert_resource(Seed(replay.seed))
+                .insert_resource(Playback::new(replay.clone()));
+        }
+
+        app .insert_resource(Recording {
+                replay: None,
+                save_to: self.record_to.clone(),
+                saved_at: 0,
+            })
+            .add_startup_system(start_recording)
+            .add_gameplay_system_to_stage(TickStage::First, play_inputs.label("Play Inputs"))
+            .add_gameplay_system_to_stage(TickStage::First, record_inputs.after("Play Inputs"))
+            .add_gameplay_system_to_stage(TickStage::First, play_config.label("Play Config").before("Apply Config"))
+            .add_gameplay_system_to_stage(TickStage::First, record_config.after("Play Config"))
+            .add_system_to_stage(CoreStage::Last, save_recording)
+            ;
+    }
+}
+
+/// The kind of fight, with its options
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub enum GameMode {
+    /// Against the boss
+    #[default]
+    Fight,
+    Training(Training),
+    Versus(Versus),
+}
+
+impl GameMode {
+    /// `--versus` or `--training`, the other arguments are ignored.
+    pub fn from_args() -> Self {
+        let args: Vec<String> = std::env::args().skip(1).collect();
+        if args.iter().any(|arg| arg == "--versus") {
+            GameMode::Versus(Versus::default())
+        } else if args.iter().any(|arg| arg == "--training") {
+            GameMode::Training(Training::default())
+        } else {
+            GameMode::Fight
+        }
+    }
+
+    pub fn training(&self) -> Option<Training> {
+        match self {
+            GameMode::Training(training) => Some(training.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn versus(&self) -> Option<Versus> {
+        match self {
+            GameMode::Versus(versus) => Some(versus.clone()),
+            _ => None,
+        }
+    }
+}
+
+/// A press or a release, on the keyboard, the mouse or a gamepad
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InputAction {
+    Press(KeyCode),
+    Release(KeyCode),
+    Click(MouseButton),
+    Unclick(MouseButton),
+    PadPress(GamepadButton),
+    PadRelease(GamepadButton),
+}
+
+/// A recorded fight
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Replay {
+    pub seed: u64,
+    pub arena: Location,
+    pub boss: String,
+    /// Played again with the replay, see `main`
+    #[serde(default)]
+    pub mode: GameMode,
+    /// The config in use from each tick on: the first one, then the reloads
+    #[serde(default)]
+    pub configs: Vec<(u64, GameplayConfig)>,
+    /// Only the ticks with an input, in order.
+    ///
+    /// The actions of a tick are played in order.
+    pub inputs: Vec<(u64, Vec<InputAction>)>,
+}
+
+impl Replay {
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Replay, ReplayError> {
+        let text = std::fs::read_to_string(path).map_err(ReplayError::Io)?;
+        ron::from_str(&text).map_err(ReplayError::Parse)
+    }
+
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> Result<(), ReplayError> {
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(ReplayError::Write)?;
+        std::fs::write(path, text).map_err(ReplayError::Io)
+    }
+
+    /// The tick of the last input
+    pub fn duration(&self) -> u64 {
+        self.inputs.last().map_or(0, |(tick, _)| *tick)
+    }
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(std::io::Error),
+    Parse(ron::error::SpannedError),
+    Write(ron::Error),
+    /// `--replay` or `--record` without its file
+    MissingPath(String),
+}
+
+impl std::fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReplayError::Io(e) => write!(f, "{}", e),
+            ReplayError::Parse(e) => write!(f, "not a replay: {}", e),
+            ReplayError::Write(e) => write!(f, "{}", e),
+            ReplayError::MissingPath(arg) => write!(f, "{} needs a file", arg),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+/// The fight so far.
+///
+/// Started once the seed is known, see `start_recording`.
+#[derive(Resource)]
+pub struct Recording {
+    pub replay: Option<Replay>,
+    pub save_to: Option<PathBuf>,
+    /// Tick of the last save
+    pub saved_at: u64,
+}
+
+/// Replaces the devices while a replay is played.
+#[derive(Resource)]
+pub struct Playback {
+    replay: Replay,
+    /// Index of the next inputs to play
+    next: usize,
+    keys: Input<KeyCode>,
+    buttons: Input<MouseButton>,
+    gamepad_buttons: Input<GamepadButton>,
+}
+
+impl Playback {
+    pub fn new(replay: Replay) -> Self {
+        Playback {
+            replay,
+            next: 0,
+            keys: default(),
+            buttons: default(),
+            gamepad_buttons: default(),
+        }
+    }
+}
+
+fn start_recording(
+    seed: Res<Seed>,
+    location: Option<Res<State<Location>>>,
+    training: Option<Res<Training>>,
+    versus: Option<Res<Versus>>,
+    playback: Option<Res<Playback>>,
+    mut recording: ResMut<Recording>,
+) {
+    let arena = location.map_or_else(default, |location| location.current().clone());
+    let mode = match (training, versus) {
+        (_, Some(versus)) => GameMode::Versus((*versus).clone()),
+        (Some(training), None) => GameMode::Training((*training).clone()),
+        (None, None) => GameMode::Fight,
+    };
+
+    if let Some(playback) = playback {
+        let replay = &playback.replay;
+        if replay.arena != arena || replay.boss != BOSS_ID || replay.mode != mode {
+            warn!(
+                "The replay was recorded against {} in {:?} ({:?}), not {} in {:?} ({:?})",
+                replay.boss, replay.arena, replay.mode, BOSS_ID, arena, mode
+            );
+        }
+    }
+
+    recording.replay = Some(Replay {
+        seed: **seed,
+        arena,
+        boss: BOSS_ID.to_string(),
+        mode,
+        configs: Vec::new(),
+        inputs: Vec::new(),
+    });
+}
+
+/// Gives the ticks the config of the replay, whatever the file says.
+fn play_config(
+    tick: Res<SimulationTick>,
+    playback: Option<Res<Playback>>,
+    mut config: ResMut<GameplayConfig>,
+) {
+    let playback = match playback {
+        Some(playback) => playback,
+        None => return,
+    };
+
+    // The last one in use at this tick
+    let recorded = playback
+        .replay
+        .configs
+        .iter()
+        .rev()
+        .find(|(config_tick, _)| *config_tick <= **tick);
+    if let Some((_, recorded)) = recorded {
+        if *recorded != *config {
+            *config = recorded.clone();
+        }
+    }
+}
+
+fn record_config(
+    tick: Res<SimulationTick>,
+    config: Res<GameplayConfig>,
+    mut recording: ResMut<Recording>,
+) {
+    if let Some(replay) = &mut recording.replay {
+        if replay.configs.last().map(|(_, recorded)| recorded) != Some(&*config) {
+            replay.configs.push((**tick, config.clone()));
+        }
+    }
+}
+
+/// Give the inputs of the replay to the tick, instead of the devices'.
+fn play_inputs(
+    mut commands: Commands,
+    tick: Res<SimulationTick>,
+    playback: Option<ResMut<Playback>>,
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    mut buttons: ResMut<Input<MouseButton>>,
+    mut gamepad_buttons: ResMut<Input<GamepadButton>>,
+) {
+    let mut playback = match playback {
+        Some(playback) => playback,
+        None => return,
+    };
+
+    if playback.next == playback.replay.inputs.len() {
+        info!("End of the replay, at the tick {}", **tick);
+        // Give the control back, without the keys held in the replay
+        keyboard_input.reset_all();
+        buttons.reset_all();
+        gamepad_buttons.reset_all();
+        commands.remove_resource::<Playback>();
+        return;
+    }
+
+    // The presses of the last tick are over
+    playback.keys.clear();
+    playback.buttons.clear();
+    playback.gamepad_buttons.clear();
+
+    while let Some((input_tick, actions)) = playback.replay.inputs.get(playback.next).cloned() {
+        if input_tick > **tick {
+            break;
+        }
+        for action in actions {
+            match action {
+                InputAction::Press(key) => playback.keys.press(key),
+                InputAction::Release(key) => playback.keys.release(key),
+                InputAction::Click(button) => playback.buttons.press(button),
+                InputAction::Unclick(button) => playback.buttons.release(button),
+                InputAction::PadPress(button) => playback.gamepad_buttons.press(button),
+                InputAction::PadRelease(button) => playback.gamepad_buttons.release(button),
+            }
+        }
+        playback.next += 1;
+    }
+
+    *keyboard_input = playback.keys.clone();
+    *buttons = playback.buttons.clone();
+    *gamepad_buttons = playback.gamepad_buttons.clone();
+}
+
+fn record_inputs(
+    tick: Res<SimulationTick>,
+    keyboard_input: Res<Input<KeyCode>>,
+    buttons: Res<Input<MouseButton>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    mut recording: ResMut<Recording>,
+) {
+    if let Some(replay) = &mut recording.replay {
+        let mut actions = input_actions(&keyboard_input, InputAction::Press, InputAction::Release);
+        actions.extend(input_actions(
+            &buttons,
+            InputAction::Click,
+            InputAction::Unclick,
+        ));
+        actions.extend(input_actions(
+            &gamepad_buttons,
+            InputAction::PadPress,
+            InputAction::PadRelease,
+        ));
+
+        if !actions.is_empty() {
+            replay.inputs.push((**tick, actions));
+        }
+    }
+}
+
+/// The actions which lead to the input state of this tick.
+///
+/// A key released then pressed again (still pressed) is released first.
+fn input_actions<T: Copy + Eq + Hash + Send + Sync + 'static>(
+    input: &Input<T>,
+    press: fn(T) -> InputAction,
+    release: fn(T) -> InputAction,
+) -> Vec<InputAction> {
+    let released_then_pressed = input
+        .get_just_released()
+        .filter(|key| input.pressed(**key))
+        .map(|key| release(*key));
+    let pressed = input.get_just_pressed().map(|key| press(*key));
+    let released = input
+        .get_just_released()
+        .filter(|key| !input.pressed(**key))
+        .map(|key| release(*key));
+
+    released_then_pressed
+        .chain(pressed)
+        .chain(released)
+        .collect()
+}
+
+/// Every `REPLAY_SAVE_INTERVAL` ticks, and on exit:
+/// a crash loses the last seconds of the fight only.
+fn save_recording(
+    tick: Res<SimulationTick>,
+    mut app_exit_events: EventReader<AppExit>,
+    mut recording: ResMut<Recording>,
+) {
+    let exit = app_exit_events.iter().next().is_some();
+    if !exit && **tick < recording.saved_at + REPLAY_SAVE_INTERVAL {
+        return;
+    }
+    recording.saved_at = **tick;
+
+    if let (Some(replay), Some(path)) = (&recording.replay, &recording.save_to) {
+        match (replay.save(path), exit) {
+            (Ok(()), true) => info!("Fight recorded in {}", path.display()),
+            (Ok(()), false) => {}
+            (Err(e), _) => error!("Can't record the fight in {}: {}", path.display(), e),
+        }
+    }
+}
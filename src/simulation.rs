@@ -0,0 +1,458 @@
+//! Fixed Timestep Simulation
+//!
+//! The gameplay (movement, timers, AI, damage and physics) runs in the `GameplayStage`,
+//! by ticks of exactly `TICK`: as many ticks as the elapsed time allows each frame.
+//! During a tick, `Time` is the simulation clock, not the frame one.
+//! The systems of a tick (and of the startup) run one after the other,
+//! always in the same order, see `OrderedExecutor`.
+//!
+//! With the same `Seed`, the same inputs always produce the same fight.
+//!
+//! The ticks can be paused, stepped one by one or slowed down, see `SimulationControl`.
+
+use std::hash::Hash;
+
+use bevy::{
+    app::StartupSchedule,
+    ecs::{
+        event::Event,
+        schedule::{ParallelSystemExecutor, SystemContainer},
+    },
+    prelude::*,
+    utils::HashSet,
+};
+use bevy_rapier2d::prelude::*;
+use rand::{rngs::StdRng, SeedableRng};
+
+use crate::constants::simulation::{MAX_TICKS_PER_FRAME, TICK};
+
+pub struct SimulationPlugin;
+
+impl Plugin for SimulationPlugin {
+    fn build(&self, app: &mut App) {
+        // Can be inserted before or replaced during the build, to replay a fight
+        app.init_resource::<Seed>()
+            .add_startup_system_to_stage(StartupStage::PreStartup, seed_rng)
+            .init_resource::<SimulationTick>()
+            .init_resource::<SimulationControl>()
+            .add_stage_before(CoreStage::Update, GameplayStage, FixedGameplayStage::new())
+            // The spawns of the startup must happen in the same order every time
+            .stage(StartupSchedule, |startup: &mut Schedule| {
+                for stage in [
+                    StartupStage::PreStartup,
+                    StartupStage::Startup,
+                    StartupStage::PostStartup,
+                ] {
+                    startup.stage(stage, |stage: &mut SystemStage| {
+                        stage.set_executor(Box::<OrderedExecutor>::default());
+                        stage
+                    });
+                }
+                startup
+            });
+    }
+}
+
+/// The fixed timestep stage, right before `CoreStage::Update`.
+///
+/// Systems and events are added with `GameplayApp`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, StageLabel)]
+pub struct GameplayStage;
+
+/// Inside a tick
+#[derive(Debug, Clone, PartialEq, Eq, Hash, StageLabel)]
+pub enum TickStage {
+    /// The gameplay events are updated every tick
+    First,
+    Update,
+    /// Then the physics steps,
+    /// see `bevy_rapier2d::plugin::PhysicsStages`
+    Last,
+}
+
+/// Number of ticks played since the startup
+#[derive(Resource, Debug, Default, Clone, Copy, Deref)]
+pub struct SimulationTick(pub u64);
+
+/// Pause, step and slow down the ticks (debug).
+///
+/// Slowed down, a tick still simulates a `TICK`:
+/// the animations, the physics and the AI all slow down together.
+#[derive(Resource, Debug, Default, Clone)]
+pub struct SimulationControl {
+    pub paused: bool,
+    pub time_scale: TimeScale,
+    /// While paused, play a single tick at the next frame
+    pub step: bool,
+}
+
+impl SimulationControl {
+    /// Pauses, and plays the next tick only
+    pub fn step(&mut self) {
+        self.paused = true;
+        self.step = true;
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TimeScale {
+    #[default]
+    Normal,
+    Half,
+    Quarter,
+}
+
+impl TimeScale {
+    pub const ALL: [TimeScale; 3] = [TimeScale::Normal, TimeScale::Half, TimeScale::Quarter];
+
+    /// Real time played by each tick
+    pub fn tick_duration(&self) -> std::time::Duration {
+        match self {
+            TimeScale::Normal => TICK,
+            TimeScale::Half => TICK * 2,
+            TimeScale::Quarter => TICK * 4,
+        }
+    }
+
+    pub fn next(&self) -> TimeScale {
+        match self {
+            TimeScale::Normal => TimeScale::Half,
+            TimeScale::Half => TimeScale::Quarter,
+            TimeScale::Quarter => TimeScale::Normal,
+        }
+    }
+}
+
+impl std::fmt::Display for TimeScale {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TimeScale::Normal => write!(f, "1x"),
+            TimeScale::Half => write!(f, "0.5x"),
+            TimeScale::Quarter => write!(f, "0.25x"),
+        }
+    }
+}
+
+/// Seed of the `GameRng`
+#[derive(Resource, Debug, Clone, Copy, Deref)]
+pub struct Seed(pub u64);
+
+impl Default for Seed {
+    fn default() -> Self {
+        Seed(rand::random())
+    }
+}
+
+/// The only randomness allowed in the gameplay.
+///
+/// Cosmetics (sparks, screen shake) don't need to be reproduced.
+#[derive(Resource, Deref, DerefMut)]
+pub struct GameRng(pub StdRng);
+
+impl GameRng {
+    pub fn new(seed: Seed) -> GameRng {
+        GameRng(StdRng::seed_from_u64(*seed))
+    }
+}
+
+/// The `GameRng` is seeded once every plugin is built.
+fn seed_rng(mut commands: Commands, seed: Res<Seed>) {
+    info!("Seed: {}", **seed);
+    commands.insert_resource(GameRng::new(*seed));
+}
+
+pub trait GameplayApp {
+    /// Played every tick, in `TickStage::Update`
+    fn add_gameplay_system<Params>(
+        &mut self,
+        system: impl IntoSystemDescriptor<Params>,
+    ) -> &mut App;
+
+    fn add_gameplay_system_to_stage<Params>(
+        &mut self,
+        stage: TickStage,
+        system: impl IntoSystemDescriptor<Params>,
+    ) -> &mut App;
+
+    /// An event sent and read during the ticks.
+    ///
+    /// Its buffers are swapped every tick, instead of every frame:
+    /// a frame without tick doesn't lose it.
+    fn add_gameplay_event<T: Event>(&mut self) -> &mut App;
+}
+
+impl GameplayApp for App {
+    fn add_gameplay_system<Params>(
+        &mut self,
+        system: impl IntoSystemDescriptor<Params>,
+    ) -> &mut App {
+        self.add_gameplay_system_to_stage(TickStage::Update, system)
+    }
+
+    fn add_gameplay_system_to_stage<Params>(
+        &mut self,
+        stage: TickStage,
+        system: impl IntoSystemDescriptor<Params>,
+    ) -> &mut App {
+        self.stage(GameplayStage, |gameplay_stage: &mut FixedGameplayStage| {
+            gameplay_stage.schedule.add_system_to_stage(stage, system);
+            gameplay_stage
+        })
+    }
+
+    fn add_gameplay_event<T: Event>(&mut self) -> &mut App {
+        if !self.world.contains_resource::<Events<T>>() {
+            self.init_resource::<Events<T>>().stage(
+                GameplayStage,
+                |stage: &mut FixedGameplayStage| {
+                    stage
+                        .schedule
+                        .add_system_to_stage(TickStage::First, Events::<T>::update_system);
+                    stage
+                },
+            );
+        }
+        self
+    }
+}
+
+/// Plays the ticks owed to the elapsed time.
+pub struct FixedGameplayStage {
+    schedule: Schedule,
+    /// The simulation clock, swapped with the frame `Time` during a tick
+    clock: Time,
+    /// Elapsed time not yet simulated (real time, see `TimeScale`)
+    accumulator: std::time::Duration,
+    /// Inputs of the frames without tick, given to the next one
+    pending_keys: PendingInputs<KeyCode>,
+    pending_buttons: PendingInputs<MouseButton>,
+    pending_gamepad_buttons: PendingInputs<GamepadButton>,
+}
+
+impl FixedGameplayStage {
+    fn new() -> Self {
+        let mut clock = Time::default();
+        // The first tick must last `TICK` too
+        clock.update_with_instant(clock.startup());
+
+        let schedule = Schedule::default()
+            .with_stage(TickStage::First, ordered_stage())
+            .with_stage(TickStage::Update, ordered_stage())
+            .with_stage(
+                PhysicsStages::SyncBackend,
+                ordered_stage().with_system_set(RapierPhysicsPlugin::<NoUserData>::get_systems(
+                    PhysicsStages::SyncBackend,
+                )),
+            )
+            .with_stage(
+                PhysicsStages::StepSimulation,
+                ordered_stage().with_system_set(RapierPhysicsPlugin::<NoUserData>::get_systems(
+                    PhysicsStages::StepSimulation,
+                )),
+            )
+            .with_stage(
+                PhysicsStages::Writeback,
+                ordered_stage().with_system_set(RapierPhysicsPlugin::<NoUserData>::get_systems(
+                    PhysicsStages::Writeback,
+                )),
+            )
+            .with_stage(TickStage::Last, ordered_stage())
+            .with_stage(
+                PhysicsStages::DetectDespawn,
+                ordered_stage().with_system_set(RapierPhysicsPlugin::<NoUserData>::get_systems(
+                    PhysicsStages::DetectDespawn,
+                )),
+            );
+
+        FixedGameplayStage {
+            schedule,
+            clock,
+            accumulator: default(),
+            pending_keys: default(),
+            pending_buttons: default(),
+            pending_gamepad_buttons: default(),
+        }
+    }
+
+    fn run_tick(&mut self, world: &mut World) {
+        let tick = {
+            let mut tick = world.resource_mut::<SimulationTick>();
+            tick.0 += 1;
+            tick.0
+        };
+        let now = self.clock.startup() + TICK * tick as u32;
+        self.clock.update_with_instant(now);
+
+        std::mem::swap(&mut *world.resource_mut::<Time>(), &mut self.clock);
+        self.schedule.run_once(world);
+        std::mem::swap(&mut *world.resource_mut::<Time>(), &mut self.clock);
+    }
+
+    /// The ticks to play this frame.
+    ///
+    /// Beyond `MAX_TICKS_PER_FRAME`, the owed ticks are dropped.
+    fn owed_ticks(&mut self, world: &mut World) -> u32 {
+        let delta = world.resource::<Time>().delta();
+        let mut control = world.resource_mut::<SimulationControl>();
+
+        if control.paused {
+            return std::mem::take(&mut control.step) as u32;
+        }
+
+        self.accumulator += delta;
+        let tick_duration = control.time_scale.tick_duration();
+        let mut ticks = 0;
+        while self.accumulator >= tick_duration {
+            self.accumulator -= tick_duration;
+            ticks += 1;
+        }
+        ticks.min(MAX_TICKS_PER_FRAME)
+    }
+}
+
+impl Stage for FixedGameplayStage {
+    fn run(&mut self, world: &mut World) {
+        let ticks = self.owed_ticks(world);
+
+        // The ticks play on their own copy of the inputs:
+        // the systems of the frame (debug keys, ...) still see the frame's ones
+        let frame_keys = save_inputs::<KeyCode>(world);
+        let frame_buttons = save_inputs::<MouseButton>(world);
+        let frame_gamepad_buttons = save_inputs::<GamepadButton>(world);
+
+        for tick in 0..ticks {
+            if tick == 0 {
+                self.pending_keys.restore(world);
+                self.pending_buttons.restore(world);
+                self.pending_gamepad_buttons.restore(world);
+            }
+            self.run_tick(world);
+            // A press is read by one tick only
+            clear_inputs::<KeyCode>(world);
+            clear_inputs::<MouseButton>(world);
+            clear_inputs::<GamepadButton>(world);
+        }
+
+        if ticks == 0 {
+            self.pending_keys.keep(world);
+            self.pending_buttons.keep(world);
+            self.pending_gamepad_buttons.keep(world);
+        }
+
+        restore_inputs(world, frame_keys);
+        restore_inputs(world, frame_buttons);
+        restore_inputs(world, frame_gamepad_buttons);
+    }
+}
+
+fn save_inputs<T: Copy + Eq + Hash + Send + Sync + 'static>(world: &World) -> Option<Input<T>> {
+    world.get_resource::<Input<T>>().cloned()
+}
+
+fn restore_inputs<T: Copy + Eq + Hash + Send + Sync + 'static>(
+    world: &mut World,
+    frame_inputs: Option<Input<T>>,
+) {
+    if let Some(frame_inputs) = frame_inputs {
+        *world.resource_mut::<Input<T>>() = frame_inputs;
+    }
+}
+
+fn ordered_stage() -> SystemStage {
+    SystemStage::new(Box::<OrderedExecutor>::default())
+}
+
+/// Runs the systems one after the other, and applies their commands right away.
+///
+/// Bevy sorts the systems of a stage with a randomly seeded hash:
+/// the ambiguous ones (without `before`/`after` between them) are run by name instead.
+#[derive(Default)]
+pub struct OrderedExecutor {
+    order: Vec<usize>,
+}
+
+impl ParallelSystemExecutor for OrderedExecutor {
+    fn rebuild_cached_data(&mut self, systems: &[SystemContainer]) {
+        self.order.clear();
+        let mut remaining: Vec<usize> = (0..systems.len()).collect();
+
+        while !remaining.is_empty() {
+            // The dependencies can't be cyclic: the stage already sorted them
+            let (position, _) = remaining
+                .iter()
+                .enumerate()
+                .filter(|(_, index)| {
+                    systems[**index]
+                        .dependencies()
+                        .iter()
+                        .all(|dependency| self.order.contains(dependency))
+                })
+                .min_by_key(|(_, index)| systems[**index].name())
+                .expect("cyclic dependencies");
+            self.order.push(remaining.remove(position));
+        }
+    }
+
+    fn run_systems(&mut self, systems: &mut [SystemContainer], world: &mut World) {
+        for index in &self.order {
+            let system = &mut systems[*index];
+            if system.should_run() {
+                system.system_mut().run((), world);
+                system.system_mut().apply_buffers(world);
+            }
+        }
+    }
+}
+
+fn clear_inputs<T: Copy + Eq + Hash + Send + Sync + 'static>(world: &mut World) {
+    if let Some(mut input) = world.get_resource_mut::<Input<T>>() {
+        input.clear();
+    }
+}
+
+/// Just pressed/released inputs of the frames without tick.
+struct PendingInputs<T> {
+    just_pressed: HashSet<T>,
+    just_released: HashSet<T>,
+}
+
+impl<T> Default for PendingInputs<T> {
+    fn default() -> Self {
+        PendingInputs {
+            just_pressed: HashSet::default(),
+            just_released: HashSet::default(),
+        }
+    }
+}
+
+impl<T: Copy + Eq + Hash + Send + Sync + 'static> PendingInputs<T> {
+    fn keep(&mut self, world: &World) {
+        if let Some(input) = world.get_resource::<Input<T>>() {
+            self.just_pressed.extend(input.get_just_pressed());
+            self.just_released.extend(input.get_just_released());
+        }
+    }
+
+    fn restore(&mut self, world: &mut World) {
+        if let Some(mut input) = world.get_resource_mut::<Input<T>>() {
+            for key in self.just_pressed.drain() {
+                let still_pressed = input.pressed(key);
+                input.reset(key);
+                input.press(key);
+                if !still_pressed {
+                    input.release(key);
+                }
+            }
+            for key in self.just_released.drain() {
+                if !input.pressed(key) && !input.just_released(key) {
+                    let just_pressed = input.just_pressed(key);
+                    input.press(key);
+                    input.release(key);
+                    if !just_pressed {
+                        input.clear_just_pressed(key);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -1,49 +1,127 @@
+use std::f32::consts::PI;
+
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
 
 use crate::{
     characters::{
-        aggression::Hp,
-        animations::CharacterState,
+        aggression::{AttackCooldown, DeadBody, Hp},
+        animations::{AnimationTimer, CharacterState},
+        corpses::{DeathOrder, FadingOut, Reclaimable},
+        input_map::{Control, Controls, InputMap},
+        npcs::boss::{Boss, BossBehaviour},
         player::{CreatePlayerEvent, Player, PlayerDeathEvent},
-        DeadBody,
     },
-    constants::character::CHAR_POSITION,
+    collisions::Team,
+    combat_log::{CombatEvent, CombatLogger},
+    constants::{
+        character::{CHAR_POSITION, CHAR_Z, FRAME_TIME},
+        soul_shift::*,
+    },
     crowd::CrowdMember,
+    simulation::GameplayApp,
 };
 
 pub struct SoulShiftPlugin;
 
 impl Plugin for SoulShiftPlugin {
     fn build(&self, app: &mut App) {
-        app.add_event::<SoulShiftEvent>()
-            .add_system(start_soul_shift.label("Soul Shift"))
-            .add_system(suicide_to_soul_shift);
+        app.add_gameplay_event::<SoulShiftEvent>()
+            .add_gameplay_event::<SeatLeftEvent>()
+            .add_gameplay_event::<PossessionEvent>()
+            .add_gameplay_system(start_soul_shift.label("Soul Shift"))
+            .add_gameplay_system(suicide_to_soul_shift.before("Soul Shift"))
+            .add_gameplay_system(possess_the_boss.after("Damage Hit"))
+            // -- Transition --
+            .add_gameplay_system(slow_motion_timer)
+            .add_gameplay_system(soul_spirit_travel.after("Soul Shift"))
+            .add_gameplay_system(join_the_fight.before("New Beginning"));
     }
 }
 
-// DEBUG: Remove it maybe ?
+/// The body is being left or taken by a soul.
+///
+/// The new body keeps it until `characters::player::create_player`,
+/// which locks any input during the whole transfer.
 #[derive(Component)]
 pub struct SoulShifting;
 
+/// A body without soul, waiting for the next soul shift:
+/// it is chosen before the spectators and the corpses.
+#[derive(Component)]
+pub struct Possessable;
+
+/// The soul travelling from the corpse to the chosen spectator.
+#[derive(Component)]
+pub struct SoulSpirit {
+    /// The new body
+    pub target: Entity,
+}
+
+/// The spectator leaving the grandstand to take the place of the dead.
+#[derive(Component)]
+pub struct JoiningTheFight {
+    /// Place in the grandstand
+    pub start: Transform,
+    pub timer: Timer,
+}
+
+/// While this resource exists, the whole game is in slow motion.
+///
+/// The timer is ticked with the real time.
+#[derive(Resource, Deref, DerefMut)]
+pub struct SlowMotion(pub Timer);
+
 /// Happens when
 ///   - soul_shift::suicide_to_soul_shift
-///     - press e
+///     - press e (see `InputMap::give_up`)
 ///   - characters::aggression::damage_hit
 ///     - Player's hp is = 0
+///   - soul_shift::possess_the_boss
+///     - The killer leaves their body for the boss's one
 ///
 /// Read in
 ///   - soul_shift::start_soul_shift
 ///     - SOUL SHIFTING // DOC: short explanation on whats happening
+#[derive(Debug, Clone)]
 pub struct SoulShiftEvent(pub Entity);
 
+/// Happens when
+///   - soul_shift::start_soul_shift
+///     - A spectator is chosen as the new body
+///
+/// Read in
+///   - training::free_soul_shift
+///     - Someone else takes the empty seat
+#[derive(Debug, Clone)]
+pub struct SeatLeftEvent {
+    pub spectator: Entity,
+    /// Where they were sitting
+    pub seat: Transform,
+}
+
+/// Happens when
+///   - characters::aggression::damage_hit
+///     - Non-player's hp is = 0
+///
+/// Read in
+///   - soul_shift::possess_the_boss
+///     - The killer takes the body of the boss
+#[derive(Debug, Clone)]
+pub struct PossessionEvent {
+    /// The defeated
+    pub body: Entity,
+    /// The killer
+    pub by: Entity,
+}
+
 fn suicide_to_soul_shift(
-    keyboard_input: Res<Input<KeyCode>>,
+    controls: Controls,
     mut soul_shift_event: EventWriter<SoulShiftEvent>,
-    mut player_query: Query<(Entity, &mut Hp), (With<Player>, Without<DeadBody>)>,
+    mut player_query: Query<(Entity, &mut Hp, &InputMap), (With<Player>, Without<DeadBody>)>,
 ) {
-    if keyboard_input.just_pressed(KeyCode::E) {
-        if let Ok((player, mut hp)) = player_query.get_single_mut() {
+    for (player, mut hp, input_map) in player_query.iter_mut() {
+        if controls.just_pressed(input_map, Control::GiveUp) {
             // So long
             hp.current = 0;
             soul_shift_event.send(SoulShiftEvent(player));
@@ -51,20 +129,71 @@ fn suicide_to_soul_shift(
     }
 }
 
+/// The defeated boss loses their soul, and their killer takes their body.
+///
+/// The boss's AI and attack range are gone:
+/// the body is rebuilt with the boss's `Moveset` once possessed,
+/// see `characters::player::create_player`.
+fn possess_the_boss(
+    mut commands: Commands,
+    mut possession_event: EventReader<PossessionEvent>,
+
+    boss_query: Query<(), With<Boss>>,
+    mut killer_query: Query<&mut Hp, (With<Player>, Without<DeadBody>, Without<Boss>)>,
+    mut soul_shift_event: EventWriter<SoulShiftEvent>,
+) {
+    // Two lethal hits of the same attack
+    let mut possessed = Vec::new();
+
+    for PossessionEvent { body, by } in possession_event.iter() {
+        if !boss_query.contains(*body) || possessed.contains(body) {
+            continue;
+        }
+
+        match killer_query.get_mut(*by) {
+            Err(e) => warn!("The boss has been defeated by no player: {:?}", e),
+            Ok(mut killer_hp) => {
+                possessed.push(*body);
+                commands
+                    .entity(*body)
+                    .remove::<(Boss, BossBehaviour, AttackCooldown)>()
+                    .insert(Possessable)
+                    .despawn_descendants();
+
+                // The old body is left behind
+                killer_hp.current = 0;
+                soul_shift_event.send(SoulShiftEvent(*by));
+            }
+        }
+    }
+}
+
 pub fn start_soul_shift(
     mut commands: Commands,
+    mut time: ResMut<Time>,
 
     mut soul_shift_event: EventReader<SoulShiftEvent>,
 
-    mut crowd_member_query: Query<(Entity, &mut Transform), (With<CrowdMember>, Without<Player>)>,
+    possessable_query: Query<(Entity, &Transform), (With<Possessable>, Without<Player>)>,
+    crowd_member_query: Query<(Entity, &Transform), (With<CrowdMember>, Without<Player>)>,
+    reclaimable_corpse_query: Query<
+        (Entity, &Transform),
+        (
+            With<DeadBody>,
+            With<Reclaimable>,
+            Without<FadingOut>,
+            Without<Player>,
+        ),
+    >,
     mut player_query: Query<
         (
             Entity,
             &mut Transform,
-            &mut CharacterState,
             &mut Velocity,
             &Hp,
             &Name,
+            Option<&Team>,
+            Option<&InputMap>,
         ),
         (
             With<Player>,
@@ -74,8 +203,12 @@ pub fn start_soul_shift(
         ),
     >,
     mut death_event: EventWriter<PlayerDeathEvent>,
-    mut create_player_event: EventWriter<CreatePlayerEvent>,
+    mut seat_left_event: EventWriter<SeatLeftEvent>,
+    mut combat_log: CombatLogger,
 ) {
+    // Two souls can't take the same body
+    let mut taken = Vec::new();
+
     for SoulShiftEvent(entity) in soul_shift_event.iter() {
         match player_query.get_mut(*entity) {
             Err(e) => warn!(
@@ -86,35 +219,40 @@ pub fn start_soul_shift(
             Ok((
                 player_entity,
                 mut player_transform,
-                mut player_state,
                 mut player_velocity,
                 player_hp,
                 player_name,
+                team,
+                input_map,
             )) => {
                 if player_hp.current == 0 {
-                    info!("Successfull Soul Shift Event");
-
-                    let mut closest_member = None;
-                    let mut min_distance = f32::MAX;
-
-                    for (entity, transform) in crowd_member_query.iter() {
-                        let distance = player_transform.translation.distance(transform.translation);
+                    let from = player_transform.translation;
+                    let available = |(entity, _): &(Entity, &Transform)| !taken.contains(entity);
 
-                        if distance < min_distance {
-                            min_distance = distance;
-                            closest_member = Some(entity);
-                        }
-                    }
+                    // A body waiting for a soul comes first,
+                    // and a fresh corpse is as good as a spectator
+                    let possessable = possessable_query.iter().filter(available);
+                    let others = crowd_member_query
+                        .iter()
+                        .chain(reclaimable_corpse_query.iter())
+                        .filter(available);
+                    let closest_member =
+                        closest_body(from, possessable).or_else(|| closest_body(from, others));
 
                     let closest_member = match closest_member {
                         Some(e) => e,
                         // TODO: End of the Game (no more life left) Sadge !
                         None => return,
                     };
+                    taken.push(closest_member);
+                    combat_log.log(CombatEvent::SoulShift {
+                        from: player_entity,
+                        to: closest_member,
+                    });
 
                     // ------- Kill for good the old body -------
 
-                    *player_state = CharacterState::Dead;
+                    // The death animation is started by player_death_event
                     commands
                         .entity(player_entity)
                         .insert((SoulShifting, DeadBody))
@@ -123,25 +261,77 @@ pub fn start_soul_shift(
                     // ^^^^^^------ System Ordering
                     death_event.send(PlayerDeathEvent(player_entity));
 
+                    player_velocity.linvel = Vect::ZERO;
+                    // player_transform.translation.z = CROWD_Z;
+                    player_transform.translation.y = CHAR_POSITION.1 - 5.0;
+
+                    // ------- Free the soul -------
+
+                    // The new body will only be created when the soul reaches it
+                    // and the spectator has joined the fight:
+                    // see soul_spirit_travel and join_the_fight
+                    commands.spawn((
+                        SpriteBundle {
+                            sprite: Sprite {
+                                color: Color::rgba(0.6, 0.9, 1., 0.8),
+                                custom_size: Some(Vec2::splat(SOUL_SPIRIT_SIZE)),
+                                ..default()
+                            },
+                            transform: Transform::from_xyz(
+                                player_transform.translation.x,
+                                player_transform.translation.y,
+                                CHAR_Z + 1.,
+                            ),
+                            ..default()
+                        },
+                        SoulSpirit {
+                            target: closest_member,
+                        },
+                        Name::new("Soul"),
+                    ));
+
+                    time.set_relative_speed(SLOW_MOTION_SPEED);
+                    commands.insert_resource(SlowMotion(Timer::from_seconds(
+                        SLOW_MOTION_DURATION,
+                        TimerMode::Once,
+                    )));
+
                     // ------- Update new player -------
 
-                    {
-                        let mut transform = crowd_member_query
-                            .get_component_mut::<Transform>(closest_member)
-                            .unwrap();
-                        transform.translation.y = CHAR_POSITION.1;
-                        transform.translation.z = CHAR_POSITION.2;
+                    if let Ok((spectator, seat)) = crowd_member_query.get(closest_member) {
+                        seat_left_event.send(SeatLeftEvent {
+                            spectator,
+                            seat: *seat,
+                        });
                     }
 
-                    player_velocity.linvel = Vect::ZERO;
-                    // player_transform.translation.z = CROWD_Z;
-                    player_transform.translation.y = CHAR_POSITION.1 - 5.0;
+                    if reclaimable_corpse_query.contains(closest_member) {
+                        // Raise the dead
+                        commands
+                            .entity(closest_member)
+                            .insert(AnimationTimer(Timer::from_seconds(
+                                FRAME_TIME,
+                                TimerMode::Repeating,
+                            )))
+                            .remove::<(DeadBody, DeathOrder, Reclaimable)>()
+                            // Its hurtbox is spawned again with the new player
+                            .despawn_descendants();
+                    }
 
+                    if possessable_query.contains(closest_member) {
+                        commands.entity(closest_member).remove::<Possessable>();
+                    }
+
+                    // The soul keeps its side and its controls
                     commands
                         .entity(closest_member)
-                        .insert((Player, SoulShifting))
-                        .remove::<CrowdMember>();
-                    create_player_event.send(CreatePlayerEvent(closest_member));
+                        .insert((
+                            Player,
+                            SoulShifting,
+                            team.copied().unwrap_or_default(),
+                            input_map.copied().unwrap_or_default(),
+                        ))
+                        .remove::<(CrowdMember, CollisionGroups)>();
                 } else {
                     warn!(
                         "This entity {}:{:?} is not yet dying",
@@ -152,3 +342,118 @@ pub fn start_soul_shift(
         }
     }
 }
+
+/// The nearest of the bodies.
+///
+/// As seen on screen: the depth (z) of the grandstand rows doesn't count.
+fn closest_body<'a>(
+    from: Vec3,
+    bodies: impl Iterator<Item = (Entity, &'a Transform)>,
+) -> Option<Entity> {
+    bodies
+        .map(|(entity, transform)| {
+            let distance = from.truncate().distance(transform.translation.truncate());
+            (entity, distance)
+        })
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(entity, _)| entity)
+}
+
+/// Bring back the normal flow of time when the slow motion is over.
+fn slow_motion_timer(
+    mut commands: Commands,
+    mut time: ResMut<Time>,
+    slow_motion: Option<ResMut<SlowMotion>>,
+) {
+    if let Some(mut slow_motion) = slow_motion {
+        // The slow motion must not slow itself
+        let raw_delta = time.raw_delta();
+        slow_motion.tick(raw_delta);
+
+        if slow_motion.just_finished() {
+            time.set_relative_speed(1.);
+            commands.remove_resource::<SlowMotion>();
+        }
+    }
+}
+
+/// The soul floats toward the new body.
+/// When reached, the spectator starts to join the fight.
+fn soul_spirit_travel(
+    mut commands: Commands,
+    time: Res<Time>,
+
+    mut soul_query: Query<(Entity, &SoulSpirit, &mut Transform)>,
+    body_query: Query<&Transform, Without<SoulSpirit>>,
+) {
+    for (soul, SoulSpirit { target }, mut soul_transform) in soul_query.iter_mut() {
+        match body_query.get(*target) {
+            Err(e) => {
+                warn!("The soul has lost its new body: {:?}", e);
+                commands.entity(soul).despawn();
+            }
+            Ok(body_transform) => {
+                let mut destination = body_transform.translation;
+                destination.z = soul_transform.translation.z;
+
+                let to_destination = destination - soul_transform.translation;
+                // The soul isn't affected by the slow motion
+                let step = SOUL_SPIRIT_SPEED * time.raw_delta_seconds();
+
+                if to_destination.length() <= step.max(SOUL_SPIRIT_REACH) {
+                    commands.entity(soul).despawn();
+                    commands.entity(*target).insert(JoiningTheFight {
+                        start: *body_transform,
+                        timer: Timer::from_seconds(JOIN_THE_FIGHT_DURATION, TimerMode::Once),
+                    });
+                } else {
+                    soul_transform.translation += to_destination.normalize() * step;
+                }
+            }
+        }
+    }
+}
+
+/// The spectator jumps from the grandstand to the fight plane.
+/// Once landed, the new player is created.
+fn join_the_fight(
+    mut commands: Commands,
+    time: Res<Time>,
+
+    mut new_body_query: Query<(
+        Entity,
+        &mut JoiningTheFight,
+        &mut Transform,
+        &mut CharacterState,
+    )>,
+    mut create_player_event: EventWriter<CreatePlayerEvent>,
+) {
+    for (new_body, mut joining, mut transform, mut state) in new_body_query.iter_mut() {
+        joining.timer.tick(time.delta());
+
+        let progress = joining.timer.percent();
+        let start = joining.start.translation;
+        let landing_y = CHAR_POSITION.1;
+
+        transform.translation.y = start.y
+            + (landing_y - start.y) * progress
+            + JOIN_THE_FIGHT_JUMP_HEIGHT * (progress * PI).sin();
+        transform.translation.z = start.z + (CHAR_POSITION.2 - start.z) * progress;
+        // Back rows are smaller
+        transform.scale = joining.start.scale.lerp(Vec3::ONE, progress);
+
+        // Bypass the state machine: a raised corpse is still `Dead`
+        if *state != CharacterState::Run {
+            *state = CharacterState::Run;
+        }
+
+        if joining.timer.just_finished() {
+            transform.translation.y = landing_y;
+            transform.translation.z = CHAR_POSITION.2;
+            transform.scale = Vec3::ONE;
+
+            commands.entity(new_body).remove::<JoiningTheFight>();
+            create_player_event.send(CreatePlayerEvent(new_body));
+        }
+    }
+}
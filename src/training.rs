@@ -0,0 +1,347 @@
+//! Training Mode
+//!
+//! `--training` turns the fight into a sandbox:
+//! the boss stands still, loops an attack or mirrors the player,
+//! the fighters can't die, the soul shifts don't empty the grandstand,
+//! the damage pops above the targets and the fight can be reset at will.
+//!
+//! Everything is tuned from the `Training` panel, see `training_panel`.
+
+use bevy::prelude::*;
+use bevy_inspector_egui::{
+    bevy_egui::{EguiContext, EguiPlugin},
+    egui,
+};
+use bevy_rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    characters::{
+        aggression::{
+            AttackCooldown, Combo, ComboPenalty, DamageDealtEvent, DeadBody, Hp, Invulnerable,
+            Parrying, Poise, Undying,
+        },
+        animations::{CharacterState, SpriteAnimations},
+        movement::Dashing,
+        npcs::boss::{Boss, BossBehaviour},
+        player::Player,
+        stamina::Stamina,
+    },
+    constants::{
+        character::{
+            player::{PLAYER_ANIMATIONS, PLAYER_ANIMATIONS_ATLAS},
+            CHAR_POSITION, CHAR_Z,
+        },
+        training::*,
+    },
+    crowd::{spectator, GrandstandRow},
+    simulation::{GameRng, GameplayApp, TickStage},
+    soul_shift::{SeatLeftEvent, SoulShifting},
+};
+
+/// The training mode, if any.
+#[derive(Default)]
+pub struct TrainingPlugin {
+    pub training: Option<Training>,
+}
+
+impl Plugin for TrainingPlugin {
+    #[rustfmt::skip]
+    fn build(&self, app: &mut App) {
+        if let Some(training) = &self.training {
+            info!("Training mode");
+            app.insert_resource(training.clone())
+                .add_gameplay_system_to_stage(TickStage::First, reset_fight.label("Reset Fight"))
+                .add_gameplay_system_to_stage(TickStage::First, apply_training.after("Reset Fight"))
+                .add_gameplay_system(free_soul_shift.after("Soul Shift"))
+                // -- Cosmetics --
+                // Spawned by the tick of the hit, as the sparks of `game_feel`
+                .add_gameplay_system(spawn_damage_numbers.after("Damage Hit"))
+                .add_system(reset_key)
+                .add_system(animate_damage_numbers)
+                ;
+
+            // Not headless (e.g. the tests)
+            if app.is_plugin_added::<WindowPlugin>() {
+                if !app.is_plugin_added::<EguiPlugin>() {
+                    app.add_plugin(EguiPlugin);
+                }
+                app.add_system(training_panel);
+            }
+        }
+    }
+}
+
+/// The options of the training mode
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Training {
+    pub boss: BossBehaviour,
+    /// Neither the player nor the boss can die, see `Undying`
+    pub infinite_hp: bool,
+    /// The spectators taking the soul are replaced
+    pub free_soul_shift: bool,
+    pub damage_numbers: bool,
+    /// The fight is reset at the next tick
+    pub reset: bool,
+}
+
+impl Default for Training {
+    fn default() -> Self {
+        Training {
+            boss: BossBehaviour::Idle,
+            infinite_hp: true,
+            free_soul_shift: true,
+            damage_numbers: true,
+            reset: false,
+        }
+    }
+}
+
+/// The damage dealt by a hit, floating above the target
+#[derive(Component, Deref, DerefMut)]
+pub struct DamageNumber(pub Timer);
+
+/// Gives the options to the fighters, the new bodies included.
+fn apply_training(
+    mut commands: Commands,
+    training: Res<Training>,
+    mut boss_query: Query<&mut BossBehaviour, With<Boss>>,
+    fighter_query: Query<
+        (Entity, Option<&Undying>),
+        (Or<(With<Player>, With<Boss>)>, Without<DeadBody>),
+    >,
+) {
+    for mut behaviour in &mut boss_query {
+        if *behaviour != training.boss {
+            *behaviour = training.boss.clone();
+        }
+    }
+
+    for (fighter, undying) in &fighter_query {
+        match (training.infinite_hp, undying) {
+            (true, None) => {
+                commands.entity(fighter).insert(Undying);
+            }
+            (false, Some(_)) => {
+                commands.entity(fighter).remove::<Undying>();
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Someone else takes the seat of the spectator who took the soul.
+fn free_soul_shift(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    training: Res<Training>,
+    mut rand: ResMut<GameRng>,
+    mut seat_left_event: EventReader<SeatLeftEvent>,
+    spectator_query: Query<(&Parent, &GrandstandRow)>,
+) {
+    for SeatLeftEvent {
+        spectator: left,
+        seat,
+    } in seat_left_event.iter()
+    {
+        if !training.free_soul_shift {
+            continue;
+        }
+
+        match spectator_query.get(*left) {
+            Err(e) => warn!("The spectator has left the grandstand: {:?}", e),
+            Ok((crowd, row)) => {
+                let replacement = spectator(
+                    *row,
+                    seat.translation.x,
+                    asset_server.load(PLAYER_ANIMATIONS_ATLAS),
+                    asset_server.load::<SpriteAnimations, _>(PLAYER_ANIMATIONS),
+                    &mut rand,
+                );
+                commands.entity(**crowd).with_children(|parent| {
+                    parent.spawn(replacement);
+                });
+            }
+        }
+    }
+}
+
+/// Back to the start of the fight: full health, in place and ready to go.
+///
+/// The corpses and the crowd stay as they are.
+fn reset_fight(
+    mut commands: Commands,
+    mut training: ResMut<Training>,
+    mut fighter_query: Query<
+        (
+            Entity,
+            &mut Hp,
+            &mut Transform,
+            &mut Velocity,
+            &mut CharacterState,
+            Option<&mut Poise>,
+            Option<&mut Stamina>,
+            Option<&mut Combo>,
+            Option<&Children>,
+        ),
+        (
+            Or<(With<Player>, With<Boss>)>,
+            Without<DeadBody>,
+            Without<SoulShifting>,
+        ),
+    >,
+    cooldown_query: Query<Entity, With<AttackCooldown>>,
+) {
+    if !training.reset {
+        return;
+    }
+    training.reset = false;
+
+    for (
+        fighter,
+        mut hp,
+        mut transform,
+        mut velocity,
+        mut state,
+        poise,
+        stamina,
+        combo,
+        children,
+    ) in &mut fighter_query
+    {
+        hp.current = hp.max;
+        transform.translation.x = CHAR_POSITION.0;
+        transform.translation.y = CHAR_POSITION.1;
+        velocity.linvel = Vect::ZERO;
+        if *state != CharacterState::Idle {
+            *state = CharacterState::Idle;
+        }
+        if let Some(mut poise) = poise {
+            poise.current = poise.max;
+        }
+        if let Some(mut stamina) = stamina {
+            stamina.current = stamina.max;
+        }
+        if let Some(mut combo) = combo {
+            *combo = Combo::default();
+        }
+
+        commands.entity(fighter).remove::<(
+            Invulnerable,
+            Parrying,
+            Dashing,
+            ComboPenalty,
+            AttackCooldown,
+        )>();
+        // The boss holds their cooldown in their range sensor
+        for child in children.into_iter().flatten() {
+            if cooldown_query.contains(*child) {
+                commands.entity(*child).remove::<AttackCooldown>();
+            }
+        }
+    }
+}
+
+fn reset_key(keyboard_input: Res<Input<KeyCode>>, mut training: ResMut<Training>) {
+    if keyboard_input.just_pressed(RESET_KEY) {
+        training.reset = true;
+    }
+}
+
+/// A number for each hit, with the damage actually dealt
+fn spawn_damage_numbers(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    training: Res<Training>,
+    mut damage_dealt_event: EventReader<DamageDealtEvent>,
+    target_query: Query<&GlobalTransform>,
+) {
+    if !training.damage_numbers {
+        damage_dealt_event.clear();
+        return;
+    }
+
+    for DamageDealtEvent { target, damage, .. } in damage_dealt_event.iter() {
+        let position = match target_query.get(*target) {
+            Ok(transform) => transform.translation(),
+            Err(_) => continue,
+        };
+
+        commands.spawn((
+            Text2dBundle {
+                text: Text::from_section(
+                    damage.to_string(),
+                    TextStyle {
+                        font: asset_server.load(DAMAGE_NUMBER_FONT),
+                        font_size: DAMAGE_NUMBER_FONT_SIZE,
+                        color: DAMAGE_NUMBER_COLOR,
+                    },
+                )
+                .with_alignment(TextAlignment::CENTER),
+                transform: Transform {
+                    translation: Vec3::new(
+                        position.x,
+                        position.y + DAMAGE_NUMBER_OFFSET_Y,
+                        CHAR_Z + 2.,
+                    ),
+                    scale: Vec3::splat(DAMAGE_NUMBER_SCALE),
+                    ..default()
+                },
+                ..default()
+            },
+            DamageNumber(Timer::from_seconds(DAMAGE_NUMBER_LIFETIME, TimerMode::Once)),
+            Name::new("Damage Number"),
+        ));
+    }
+}
+
+/// The numbers rise and fade out, in real time.
+fn animate_damage_numbers(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut damage_number_query: Query<(Entity, &mut DamageNumber, &mut Transform, &mut Text)>,
+) {
+    for (damage_number, mut timer, mut transform, mut text) in &mut damage_number_query {
+        timer.tick(time.raw_delta());
+        if timer.finished() {
+            commands.entity(damage_number).despawn();
+            continue;
+        }
+
+        transform.translation.y += DAMAGE_NUMBER_RISE * time.raw_delta_seconds();
+        for section in &mut text.sections {
+            section.style.color.set_a(timer.percent_left());
+        }
+    }
+}
+
+/// The `Training` panel
+fn training_panel(mut egui_context: ResMut<EguiContext>, mut training: ResMut<Training>) {
+    egui::Window::new("Training")
+        .collapsible(true)
+        .show(egui_context.ctx_mut(), |ui| {
+            ui.label("Boss");
+            for (behaviour, label) in [
+                (BossBehaviour::Aggressive, "Aggressive"),
+                (BossBehaviour::Idle, "Idle"),
+                (BossBehaviour::Loop(CharacterState::Attack), "Loop Smash"),
+                (
+                    BossBehaviour::Loop(CharacterState::SecondAttack),
+                    "Loop Fallen Angel",
+                ),
+                (BossBehaviour::Mirror, "Mirror"),
+            ] {
+                ui.radio_value(&mut training.boss, behaviour, label);
+            }
+            ui.separator();
+
+            ui.checkbox(&mut training.infinite_hp, "Infinite HP");
+            ui.checkbox(&mut training.free_soul_shift, "Free soul shift");
+            ui.checkbox(&mut training.damage_numbers, "Damage numbers");
+            ui.separator();
+
+            if ui.button(format!("Reset ({:?})", RESET_KEY)).clicked() {
+                training.reset = true;
+            }
+        });
+}
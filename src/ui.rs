@@ -1,22 +1,84 @@
-use crate::characters::{aggression::Hp, player::Player};
+use crate::{
+    characters::{aggression::Hp, player::Player, stamina::Stamina},
+    collisions::Team,
+    versus::{player_label, Versus},
+};
 use bevy::prelude::*;
 
 pub struct UiPlugin;
 
 impl Plugin for UiPlugin {
     fn build(&self, app: &mut App) {
-        app.add_startup_system(setup_ui).add_system(update_health);
+        app.add_startup_system(setup_ui)
+            .add_system(update_health)
+            .add_system(update_stamina);
     }
 }
 
+/// The health of the players of this team
 #[derive(Component)]
-struct HealthText;
+struct HealthText {
+    team: Team,
+    label: String,
+}
+
+/// Its width is the stamina left
+#[derive(Component)]
+struct StaminaBar(Team);
+
+/// Where a HUD is anchored
+#[derive(Clone, Copy)]
+enum HudCorner {
+    TopLeft,
+    TopRight,
+}
+
+/// One HUD, or one per player in versus
+fn setup_ui(mut commands: Commands, asset_server: Res<AssetServer>, versus: Option<Res<Versus>>) {
+    if versus.is_some() {
+        for (team, corner) in [
+            (Team::Player, HudCorner::TopLeft),
+            (Team::Boss, HudCorner::TopRight),
+        ] {
+            let label = format!("{} Health", player_label(team));
+            spawn_hud(&mut commands, &asset_server, team, label, corner);
+        }
+    } else {
+        let label = String::from("Health");
+        spawn_hud(
+            &mut commands,
+            &asset_server,
+            Team::Player,
+            label,
+            HudCorner::TopRight,
+        );
+    }
+}
+
+fn spawn_hud(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    team: Team,
+    label: String,
+    corner: HudCorner,
+) {
+    let position = |top| match corner {
+        HudCorner::TopLeft => UiRect {
+            top: Val::Px(top),
+            left: Val::Px(10.0),
+            ..default()
+        },
+        HudCorner::TopRight => UiRect {
+            top: Val::Px(top),
+            right: Val::Px(10.0),
+            ..default()
+        },
+    };
 
-fn setup_ui(mut commands: Commands, asset_server: Res<AssetServer>) {
     commands
         .spawn(
             TextBundle::from_section(
-                "Health: 100%",
+                format!("{}: 100%", label),
                 TextStyle {
                     font: asset_server.load("fonts/dpcomic.ttf"),
                     font_size: 30.0,
@@ -25,26 +87,62 @@ fn setup_ui(mut commands: Commands, asset_server: Res<AssetServer>) {
             )
             .with_style(Style {
                 position_type: PositionType::Absolute,
-                position: UiRect {
-                    top: Val::Px(10.0),
-                    right: Val::Px(10.0),
-                    ..default()
-                },
+                position: position(10.0),
                 ..default()
             }),
         )
-        .insert(HealthText);
+        .insert(HealthText { team, label });
+
+    // -- Stamina --
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: position(45.0),
+                size: Size::new(Val::Px(150.0), Val::Px(10.0)),
+                ..default()
+            },
+            background_color: Color::rgb(0.2, 0.2, 0.2).into(),
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                        ..default()
+                    },
+                    background_color: Color::rgb(0.9, 0.8, 0.3).into(),
+                    ..default()
+                },
+                StaminaBar(team),
+            ));
+        });
 }
 
 fn update_health(
-    mut text_query: Query<&mut Text, With<HealthText>>,
-    hp_query: Query<&Hp, (With<Player>, Changed<Hp>)>,
+    mut text_query: Query<(&mut Text, &HealthText)>,
+    hp_query: Query<(&Hp, &Team), (With<Player>, Changed<Hp>)>,
 ) {
-    for hp in hp_query.iter() {
-        let mut text = text_query.single_mut();
-        text.sections[0] = TextSection::new(
-            format!("Health: {}%", hp.current / hp.max),
-            text.sections[0].style.clone(),
-        );
+    for (hp, team) in hp_query.iter() {
+        for (mut text, health_text) in text_query.iter_mut() {
+            if health_text.team == *team {
+                text.sections[0].value =
+                    format!("{}: {}%", health_text.label, 100 * hp.current / hp.max);
+            }
+        }
+    }
+}
+
+fn update_stamina(
+    mut bar_query: Query<(&mut Style, &StaminaBar)>,
+    stamina_query: Query<(&Stamina, &Team), (With<Player>, Changed<Stamina>)>,
+) {
+    for (stamina, team) in stamina_query.iter() {
+        for (mut style, StaminaBar(bar_team)) in bar_query.iter_mut() {
+            if bar_team == team {
+                style.size.width = Val::Percent(100.0 * stamina.current / stamina.max);
+            }
+        }
     }
 }
@@ -0,0 +1,339 @@
+//! Local Versus
+//!
+//! `--versus` puts two players in the arena, without the boss:
+//! the first one on the left half of the keyboard (or the first gamepad),
+//! the second one on the right half (or the second gamepad).
+//!
+//! Both souls shift to the same grandstand.
+//! Killing the other's body wins the round;
+//! the next one starts once the loser is back in the fight.
+
+use bevy::{prelude::*, utils::HashMap};
+use bevy_rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    characters::{
+        aggression::{DeadBody, FlipAttackSensorEvent, Hp},
+        animations::SpriteAnimations,
+        input_map::InputMap,
+        npcs::boss::Boss,
+        player::{CreatePlayerEvent, Player, PlayerDeathEvent},
+        stamina::Stamina,
+    },
+    collisions::Team,
+    constants::{
+        character::{
+            player::{PLAYER_ANIMATIONS, PLAYER_ANIMATIONS_ATLAS},
+            CHAR_POSITION,
+        },
+        versus::*,
+    },
+    crowd::CrowdTint,
+    simulation::{GameplayApp, TickStage},
+    soul_shift::SoulShifting,
+};
+
+/// The versus, if any.
+#[derive(Default)]
+pub struct VersusPlugin {
+    pub versus: Option<Versus>,
+}
+
+impl Plugin for VersusPlugin {
+    #[rustfmt::skip]
+    fn build(&self, app: &mut App) {
+        if let Some(versus) = &self.versus {
+            info!("Versus, first to {} rounds", versus.rounds_to_win);
+            app.insert_resource(versus.clone())
+                .init_resource::<Rounds>()
+                // After the spawn of the first player and the boss
+                .add_startup_system_to_stage(StartupStage::PostStartup, setup_versus)
+                .add_gameplay_system(score_round.after("Soul Shift"))
+                .add_gameplay_system_to_stage(TickStage::First, next_round)
+                // -- Cosmetics --
+                .add_startup_system(spawn_banner)
+                .add_system(update_banner)
+                .add_system(tag_fighters)
+                ;
+        }
+    }
+}
+
+/// The options of the versus
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Versus {
+    pub rounds_to_win: u32,
+}
+
+impl Default for Versus {
+    fn default() -> Self {
+        Versus {
+            rounds_to_win: ROUNDS_TO_WIN,
+        }
+    }
+}
+
+/// The score of the current match
+#[derive(Resource, Debug, Clone)]
+pub struct Rounds {
+    /// From 1
+    pub round: u32,
+    pub wins: HashMap<Team, u32>,
+    /// The round is over, until the loser is back in the fight
+    pub round_winner: Option<Team>,
+    /// The match is over, a new one starts with the next round
+    pub match_winner: Option<Team>,
+}
+
+impl Default for Rounds {
+    fn default() -> Self {
+        Rounds {
+            round: 1,
+            wins: HashMap::default(),
+            round_winner: None,
+            match_winner: None,
+        }
+    }
+}
+
+impl Rounds {
+    pub fn wins(&self, team: Team) -> u32 {
+        self.wins.get(&team).copied().unwrap_or_default()
+    }
+}
+
+/// How the players are called
+pub fn player_label(team: Team) -> &'static str {
+    match team {
+        Team::Player => "P1",
+        Team::Boss => "P2",
+    }
+}
+
+/// Where the team stands at the start of a round
+fn start_x(team: Team) -> f32 {
+    match team {
+        Team::Player => CHAR_POSITION.0 - START_DISTANCE,
+        Team::Boss => CHAR_POSITION.0 + START_DISTANCE,
+    }
+}
+
+/// The boss leaves, the first player gets the left half of the keyboard
+/// and the second player enters the arena, facing them.
+fn setup_versus(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    boss_query: Query<Entity, With<Boss>>,
+    mut player_query: Query<(Entity, &mut Transform), With<Player>>,
+    mut create_player_event: EventWriter<CreatePlayerEvent>,
+) {
+    for boss in &boss_query {
+        commands.entity(boss).despawn_recursive();
+    }
+
+    for (player, mut transform) in &mut player_query {
+        transform.translation.x = start_x(Team::Player);
+        commands.entity(player).insert(InputMap::PLAYER_ONE);
+    }
+
+    let mut position = Vec3::from(CHAR_POSITION);
+    position.x = start_x(Team::Boss);
+    let second_player = commands
+        .spawn((
+            Player,
+            SpriteSheetBundle {
+                texture_atlas: asset_server.load(PLAYER_ANIMATIONS_ATLAS),
+                sprite: TextureAtlasSprite {
+                    color: PLAYER_TWO_TINT,
+                    flip_x: true,
+                    ..default()
+                },
+                transform: Transform::from_translation(position),
+                ..default()
+            },
+            asset_server.load::<SpriteAnimations, _>(PLAYER_ANIMATIONS),
+            // Given back after the flashes
+            CrowdTint(PLAYER_TWO_TINT),
+            InputMap::PLAYER_TWO,
+            Team::Boss,
+        ))
+        .id();
+    create_player_event.send(CreatePlayerEvent(second_player));
+}
+
+/// The first body to fall loses the round.
+fn score_round(
+    versus: Res<Versus>,
+    mut rounds: ResMut<Rounds>,
+    mut death_event: EventReader<PlayerDeathEvent>,
+    team_query: Query<&Team>,
+) {
+    for PlayerDeathEvent(dead) in death_event.iter() {
+        // Already decided
+        if rounds.round_winner.is_some() {
+            continue;
+        }
+
+        match team_query.get(*dead) {
+            Err(e) => warn!("The dead has no team: {:?}", e),
+            Ok(loser) => {
+                let winner = loser.opponent();
+                *rounds.wins.entry(winner).or_default() += 1;
+                rounds.round_winner = Some(winner);
+                info!("{} wins the round {}", player_label(winner), rounds.round);
+
+                if rounds.wins(winner) >= versus.rounds_to_win {
+                    rounds.match_winner = Some(winner);
+                    info!("{} wins the match", player_label(winner));
+                }
+            }
+        }
+    }
+}
+
+/// Once both players are back in the fight, they start again from their side,
+/// fully healed.
+fn next_round(
+    mut rounds: ResMut<Rounds>,
+    mut fighter_query: Query<
+        (
+            Entity,
+            &Team,
+            &mut Hp,
+            &mut Transform,
+            &mut Velocity,
+            &mut TextureAtlasSprite,
+            Option<&mut Stamina>,
+        ),
+        (With<Player>, Without<DeadBody>),
+    >,
+    soul_shifting_query: Query<(), (With<Player>, With<SoulShifting>)>,
+    mut flip_direction_event: EventWriter<FlipAttackSensorEvent>,
+) {
+    if rounds.round_winner.is_none() || !soul_shifting_query.is_empty() {
+        return;
+    }
+
+    if rounds.match_winner.take().is_some() {
+        rounds.wins.clear();
+        rounds.round = 1;
+    } else {
+        rounds.round += 1;
+    }
+    rounds.round_winner = None;
+
+    for (fighter, team, mut hp, mut transform, mut velocity, mut sprite, stamina) in
+        &mut fighter_query
+    {
+        hp.current = hp.max;
+        if let Some(mut stamina) = stamina {
+            stamina.current = stamina.max;
+        }
+
+        transform.translation.x = start_x(*team);
+        velocity.linvel = Vect::ZERO;
+
+        // Facing each other
+        let flip_x = *team == Team::Boss;
+        if sprite.flip_x != flip_x {
+            sprite.flip_x = flip_x;
+            flip_direction_event.send(FlipAttackSensorEvent(fighter));
+        }
+    }
+}
+
+#[derive(Component)]
+struct RoundBanner;
+
+fn spawn_banner(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: asset_server.load(TAG_FONT),
+                font_size: BANNER_FONT_SIZE,
+                color: Color::WHITE,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                top: Val::Px(10.0),
+                left: Val::Percent(45.0),
+                ..default()
+            },
+            ..default()
+        }),
+        RoundBanner,
+        Name::new("Round Banner"),
+    ));
+}
+
+fn update_banner(rounds: Res<Rounds>, mut banner_query: Query<&mut Text, With<RoundBanner>>) {
+    if !rounds.is_changed() {
+        return;
+    }
+
+    let banner = match (rounds.match_winner, rounds.round_winner) {
+        (Some(winner), _) => format!("{} wins the match!", player_label(winner)),
+        (None, Some(winner)) => format!("{} wins the round", player_label(winner)),
+        (None, None) => format!(
+            "Round {}   {} - {}",
+            rounds.round,
+            rounds.wins(Team::Player),
+            rounds.wins(Team::Boss)
+        ),
+    };
+    for mut text in &mut banner_query {
+        text.sections[0].value = banner.clone();
+    }
+}
+
+/// Above the fighter, so each player finds their new body
+#[derive(Component)]
+struct FighterTag;
+
+fn tag_fighters(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    fighter_query: Query<
+        (Entity, &Team, Option<&Children>),
+        (With<Player>, Without<DeadBody>, Without<SoulShifting>),
+    >,
+    tag_query: Query<(), With<FighterTag>>,
+) {
+    for (fighter, team, children) in &fighter_query {
+        let tagged = children
+            .into_iter()
+            .flatten()
+            .any(|child| tag_query.contains(*child));
+        if tagged {
+            continue;
+        }
+
+        commands.entity(fighter).with_children(|parent| {
+            parent.spawn((
+                Text2dBundle {
+                    text: Text::from_section(
+                        player_label(*team),
+                        TextStyle {
+                            font: asset_server.load(TAG_FONT),
+                            font_size: TAG_FONT_SIZE,
+                            color: Color::WHITE,
+                        },
+                    )
+                    .with_alignment(TextAlignment::CENTER),
+                    transform: Transform {
+                        translation: Vec3::new(0., TAG_OFFSET_Y, 1.),
+                        scale: Vec3::splat(TAG_SCALE),
+                        ..default()
+                    },
+                    ..default()
+                },
+                FighterTag,
+                Name::new("Fighter Tag"),
+            ));
+        });
+    }
+}
@@ -0,0 +1,180 @@
+mod common;
+
+use common::Simulation;
+use grandstand_gauntlet::{
+    characters::{animations::CharacterState, state_machine::ChangeStateEvent},
+    combat_log::{CombatEvent, CombatLog, CombatLogExport, CombatLogPlugin},
+};
+
+/// The boss smashes the player standing still, twice.
+fn death_by_smashes() -> (Simulation, CombatLog) {
+    death_by_smashes_with(Simulation::new())
+}
+
+fn death_by_smashes_with(mut sim: Simulation) -> (Simulation, CombatLog) {
+    let player = sim.player().expect("no player");
+
+    assert!(sim.run_until(60 * 60, |sim| sim.hp(player) == 0));
+    sim.run(5);
+
+    let combat_log = sim.app.world.resource::<CombatLog>().clone();
+    (sim, combat_log)
+}
+
+#[test]
+fn every_hit_is_logged_with_the_hp_of_the_target() {
+    let (mut sim, combat_log) = death_by_smashes();
+    let boss = sim.boss();
+
+    let hits: Vec<_> = combat_log
+        .hits()
+        .map(|entry| match entry.event {
+            CombatEvent::Hit {
+                attacker,
+                damage,
+                hp_before,
+                hp_after,
+                ..
+            } => (attacker, damage, hp_before, hp_after),
+            _ => unreachable!(),
+        })
+        .collect();
+    assert_eq!(hits, vec![(boss, 10, 20, 10), (boss, 10, 10, 0)]);
+
+    // In order
+    assert!(combat_log
+        .entries
+        .windows(2)
+        .all(|entries| entries[0].tick <= entries[1].tick));
+}
+
+#[test]
+fn the_death_is_logged() {
+    let (_, combat_log) = death_by_smashes();
+
+    let (lethal_tick, dead) = combat_log
+        .hits()
+        .last()
+        .map(|entry| match entry.event {
+            CombatEvent::Hit { target, .. } => (entry.tick, target),
+            _ => unreachable!(),
+        })
+        .unwrap();
+
+    let soul_shift = combat_log
+        .entries
+        .iter()
+        .find(|entry| matches!(entry.event, CombatEvent::SoulShift { .. }))
+        .expect("no soul shift");
+    assert!(soul_shift.tick >= lethal_tick);
+    assert!(matches!(soul_shift.event, CombatEvent::SoulShift { from, .. } if from == dead));
+
+    assert!(combat_log.entries.iter().any(|entry| matches!(
+        entry.event,
+        CombatEvent::StateChange { entity, to: CharacterState::Dead, .. } if entity == dead
+    )));
+}
+
+#[test]
+fn the_combat_log_is_exported() {
+    let (_, combat_log) = death_by_smashes();
+
+    let csv = combat_log.to_csv();
+    let mut lines = csv.lines();
+    let columns = lines.next().unwrap().split(',').count();
+    assert_eq!(lines.clone().count(), combat_log.entries.len());
+    assert!(lines.all(|line| line.split(',').count() == columns));
+
+    let json: serde_json::Value = serde_json::from_str(&combat_log.to_json().unwrap()).unwrap();
+    let entries = json.as_array().unwrap();
+    assert_eq!(entries.len(), combat_log.entries.len());
+    let first_hit = entries.iter().find(|entry| entry["kind"] == "Hit").unwrap();
+    assert_eq!(first_hit["damage"], 10);
+    assert_eq!(first_hit["hp_after"], 10);
+}
+
+#[test]
+fn the_combat_log_is_exported_at_the_end_of_the_fight() {
+    let path = std::env::temp_dir().join(format!("combat-log-{}.json", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let mut sim = Simulation::new();
+    sim.app.insert_resource(CombatLogExport(Some(path.clone())));
+    let (_, combat_log) = death_by_smashes_with(sim);
+
+    let exported = std::fs::read_to_string(&path).expect("not exported");
+    std::fs::remove_file(&path).unwrap();
+    let json: serde_json::Value = serde_json::from_str(&exported).unwrap();
+    let hits = json
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|entry| entry["kind"] == "Hit")
+        .count();
+    assert_eq!(hits, combat_log.hits().count());
+}
+
+#[test]
+fn the_combat_log_arguments_leave_the_others_alone() {
+    let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+
+    let plugin = CombatLogPlugin::parse_args(args(&[
+        "--record",
+        "fight.ron",
+        "--combat-log",
+        "fight.csv",
+    ]))
+    .unwrap();
+    assert_eq!(plugin.export_to, Some("fight.csv".into()));
+    assert!(CombatLogPlugin::parse_args(args(&["--training"]))
+        .unwrap()
+        .export_to
+        .is_none());
+    assert!(CombatLogPlugin::parse_args(args(&["--combat-log"])).is_err());
+}
+
+#[test]
+fn the_state_changes_of_a_tick_are_logged_in_the_order_of_their_requests() {
+    let mut sim = Simulation::new();
+    let mut crowd: Vec<_> = sim.crowd().into_iter().map(|(entity, _)| entity).collect();
+    crowd.reverse();
+    assert!(crowd.len() > 4);
+
+    for (i, entity) in crowd.iter().enumerate() {
+        let state = if i % 2 == 0 {
+            CharacterState::Hit
+        } else {
+            CharacterState::Stunned
+        };
+        sim.app.world.send_event(ChangeStateEvent {
+            entity: *entity,
+            state,
+        });
+    }
+    // Overruled by its later, higher request: still logged first
+    sim.app.world.send_event(ChangeStateEvent {
+        entity: crowd[0],
+        state: CharacterState::Dead,
+    });
+    sim.step();
+
+    let changes: Vec<_> = sim
+        .app
+        .world
+        .resource::<CombatLog>()
+        .entries
+        .iter()
+        .filter_map(|entry| match &entry.event {
+            CombatEvent::StateChange { entity, to, .. } if crowd.contains(entity) => {
+                Some((*entity, to.clone()))
+            }
+            _ => None,
+        })
+        .collect();
+    assert_eq!(changes.len(), crowd.len());
+    assert_eq!(changes[0], (crowd[0], CharacterState::Dead));
+    assert!(changes
+        .iter()
+        .zip(&crowd)
+        .all(|((logged, _), requested)| logged == requested));
+}
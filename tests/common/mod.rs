@@ -0,0 +1,400 @@
+//! Headless simulation of a fight.
+//!
+//! The gameplay plugins (`CharacterPlugin`, `SoulShiftPlugin`, `CrowdPlugin`)
+//! run under `MinimalPlugins`: no window, no render, no texture.
+//!
+//! - The `AssetServer` can't read anything (`StubAssetIo`):
+//!   the animations are read from the assets folder and stored right away.
+//! - Each step is a frame lasting exactly one `TICK`: one tick per step.
+//! - Inputs are sent as keyboard/mouse events, as a real device would.
+//! - The fight is recorded, see `Simulation::recording`.
+
+#![allow(dead_code)]
+
+use std::{
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use bevy::{
+    asset::{AssetIo, AssetIoError, AssetPlugin, BoxedFuture, Metadata},
+    ecs::event::Event,
+    input::{keyboard::KeyboardInput, mouse::MouseButtonInput, ButtonState, InputPlugin},
+    prelude::*,
+    time::TimeUpdateStrategy,
+};
+use bevy_rapier2d::prelude::*;
+
+use grandstand_gauntlet::{
+    characters::{
+        aggression::{DeadBody, Hp},
+        animations::{CharacterState, SpriteAnimations},
+        npcs::boss::Boss,
+        player::{Player, PossesionCount},
+        CharacterPlugin,
+    },
+    collisions::Team,
+    combat_log::CombatLogPlugin,
+    config::{ConfigPlugin, GameplayConfig, GameplayConfigHandle},
+    constants::simulation::TICK,
+    constants::{
+        character::{boss::BOSS_ANIMATIONS, player::PLAYER_ANIMATIONS},
+        TILE_SIZE,
+    },
+    crowd::{CrowdMember, CrowdPlugin},
+    replay::{GameMode, Recording, Replay, ReplayPlugin},
+    simulation::{Seed, SimulationPlugin, SimulationTick},
+    soul_shift::{SoulShiftPlugin, SoulShifting},
+    training::{Training, TrainingPlugin},
+    versus::{Versus, VersusPlugin},
+};
+
+/// Seed of `Simulation::new`
+pub const SEED: u64 = 2023;
+
+/// Nothing can be read from this one.
+struct StubAssetIo;
+
+impl AssetIo for StubAssetIo {
+    fn load_path<'a>(&'a self, path: &'a Path) -> BoxedFuture<'a, Result<Vec<u8>, AssetIoError>> {
+        Box::pin(async move { Err(AssetIoError::NotFound(path.to_path_buf())) })
+    }
+
+    fn read_directory(
+        &self,
+        path: &Path,
+    ) -> Result<Box<dyn Iterator<Item = PathBuf>>, AssetIoError> {
+        Err(AssetIoError::NotFound(path.to_path_buf()))
+    }
+
+    fn get_metadata(&self, path: &Path) -> Result<Metadata, AssetIoError> {
+        Err(AssetIoError::NotFound(path.to_path_buf()))
+    }
+
+    fn watch_path_for_changes(&self, _path: &Path) -> Result<(), AssetIoError> {
+        Ok(())
+    }
+
+    fn watch_for_changes(&self) -> Result<(), AssetIoError> {
+        Ok(())
+    }
+}
+
+/// Keeps the stubbed assets alive.
+#[derive(Resource)]
+struct StubbedAnimations(Vec<Handle<SpriteAnimations>>);
+
+/// Every event `E` sent since `Simulation::record::<E>`, with its tick.
+#[derive(Resource)]
+pub struct Recorded<E: Event>(pub Vec<(u64, E)>);
+
+fn record_events<E: Event + Clone>(
+    tick: Res<SimulationTick>,
+    mut events: EventReader<E>,
+    mut recorded: ResMut<Recorded<E>>,
+) {
+    for event in events.iter() {
+        recorded.0.push((tick.0, event.clone()));
+    }
+}
+
+pub struct Simulation {
+    pub app: App,
+    start: Instant,
+    frame: u32,
+}
+
+impl Simulation {
+    pub fn new() -> Simulation {
+        Simulation::with_seed(SEED)
+    }
+
+    /// The startup and the first tick are already played:
+    /// the player, the boss and the crowd are spawned.
+    pub fn with_seed(seed: u64) -> Simulation {
+        Simulation::build(Seed(seed), ReplayPlugin::default(), GameMode::Fight)
+    }
+
+    /// The seed, the mode, the config and the inputs come from the replay:
+    /// the inputs sent to the simulation are ignored until its end.
+    pub fn replay(replay: Replay) -> Simulation {
+        let mode = replay.mode.clone();
+        Simulation::build(
+            Seed(replay.seed),
+            ReplayPlugin {
+                replay: Some(replay),
+                record_to: None,
+            },
+            mode,
+        )
+    }
+
+    /// In training mode, with these options
+    pub fn training(training: Training) -> Simulation {
+        Simulation::build(
+            Seed(SEED),
+            ReplayPlugin::default(),
+            GameMode::Training(training),
+        )
+    }
+
+    /// Two players, without the boss
+    pub fn versus(versus: Versus) -> Simulation {
+        Simulation::build(
+            Seed(SEED),
+            ReplayPlugin::default(),
+            GameMode::Versus(versus),
+        )
+    }
+
+    fn build(seed: Seed, replay_plugin: ReplayPlugin, mode: GameMode) -> Simulation {
+        let mut app = App::new();
+
+        app.insert_resource(AssetServer::new(StubAssetIo))
+            .insert_resource(seed)
+            .add_plugins(MinimalPlugins)
+            .add_plugin(AssetPlugin::default())
+            .add_plugin(TransformPlugin)
+            .add_plugin(HierarchyPlugin)
+            .add_plugin(InputPlugin)
+            // Only referenced by handles
+            .add_asset::<Image>()
+            .add_asset::<TextureAtlas>()
+            .insert_resource(RapierConfiguration {
+                gravity: Vec2::ZERO,
+                ..default()
+            })
+            .add_plugin(
+                RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(TILE_SIZE)
+                    .with_default_system_setup(false),
+            )
+            .add_plugin(SimulationPlugin)
+            .add_plugin(replay_plugin)
+            .add_plugin(CombatLogPlugin::default())
+            .add_plugin(ConfigPlugin)
+            .add_plugin(CharacterPlugin)
+            .add_plugin(SoulShiftPlugin)
+            .add_plugin(CrowdPlugin);
+
+        app.add_plugin(TrainingPlugin {
+            training: mode.training(),
+        })
+        .add_plugin(VersusPlugin {
+            versus: mode.versus(),
+        });
+
+        let stubbed_animations = [PLAYER_ANIMATIONS, BOSS_ANIMATIONS]
+            .iter()
+            .map(|path| stub_animations(&mut app, path))
+            .collect();
+        app.insert_resource(StubbedAnimations(stubbed_animations));
+
+        // The first frame lasts nothing, as the next ones last one `TICK`
+        let start = app.world.resource::<Time>().startup();
+        app.insert_resource(TimeUpdateStrategy::ManualInstant(start));
+        app.update();
+
+        let mut sim = Simulation {
+            app,
+            start,
+            frame: 0,
+        };
+        // The first frame has no elapsed time: no tick
+        sim.step();
+        sim
+    }
+
+    /// Keep every `E` sent from now on, see `Simulation::recorded`.
+    pub fn record<E: Event + Clone>(&mut self) -> &mut Self {
+        self.app
+            .insert_resource(Recorded::<E>(Vec::new()))
+            .add_system_to_stage(CoreStage::Last, record_events::<E>);
+        self
+    }
+
+    pub fn recorded<E: Event>(&self) -> &[(u64, E)] {
+        &self.app.world.resource::<Recorded<E>>().0
+    }
+
+    /// The fight so far, as a replay
+    pub fn recording(&self) -> Replay {
+        self.app
+            .world
+            .resource::<Recording>()
+            .replay
+            .clone()
+            .expect("not recording")
+    }
+
+    pub fn tick(&self) -> u64 {
+        self.app.world.resource::<SimulationTick>().0
+    }
+
+    /// Play one tick
+    pub fn step(&mut self) {
+        self.frame += 1;
+        *self.app.world.resource_mut::<TimeUpdateStrategy>() =
+            TimeUpdateStrategy::ManualInstant(self.start + TICK * self.frame);
+        self.app.update();
+    }
+
+    pub fn run(&mut self, ticks: u64) {
+        for _ in 0..ticks {
+            self.step();
+        }
+    }
+
+    pub fn run_for(&mut self, seconds: f64) {
+        self.run((seconds / TICK.as_secs_f64()).ceil() as u64);
+    }
+
+    /// Play until the condition is met, at most `max_ticks`.
+    ///
+    /// Returns if the condition was met.
+    pub fn run_until(
+        &mut self,
+        max_ticks: u64,
+        mut condition: impl FnMut(&mut Simulation) -> bool,
+    ) -> bool {
+        for _ in 0..max_ticks {
+            if condition(self) {
+                return true;
+            }
+            self.step();
+        }
+        condition(self)
+    }
+
+    // -- Inputs --
+    // Read at the next tick
+
+    pub fn press(&mut self, key: KeyCode) {
+        self.send_key(key, ButtonState::Pressed);
+    }
+
+    pub fn release(&mut self, key: KeyCode) {
+        self.send_key(key, ButtonState::Released);
+    }
+
+    /// Press on this tick, release on the next one
+    pub fn tap(&mut self, key: KeyCode) {
+        self.press(key);
+        self.step();
+        self.release(key);
+    }
+
+    pub fn click(&mut self, button: MouseButton, state: ButtonState) {
+        self.app
+            .world
+            .resource_mut::<Events<MouseButtonInput>>()
+            .send(MouseButtonInput { button, state });
+    }
+
+    fn send_key(&mut self, key: KeyCode, state: ButtonState) {
+        self.app
+            .world
+            .resource_mut::<Events<KeyboardInput>>()
+            .send(KeyboardInput {
+                scan_code: 0,
+                key_code: Some(key),
+                state,
+            });
+    }
+
+    /// As if the config file was saved with this content:
+    /// noticed at the end of the next step, applied by the one after
+    pub fn save_config(&mut self, config: GameplayConfig) {
+        let handle = self.app.world.resource::<GameplayConfigHandle>().0.clone();
+        self.app
+            .world
+            .resource_mut::<Assets<GameplayConfig>>()
+            .set_untracked(handle, config);
+    }
+
+    // -- Queries --
+
+    /// The controlled character, if any (none during the soul shift)
+    pub fn player(&mut self) -> Option<Entity> {
+        self.app
+            .world
+            .query_filtered::<Entity, (With<Player>, Without<DeadBody>, Without<SoulShifting>)>()
+            .get_single(&self.app.world)
+            .ok()
+    }
+
+    /// The controlled character of this team, if any (versus)
+    pub fn fighter(&mut self, team: Team) -> Option<Entity> {
+        self.app
+            .world
+            .query_filtered::<(Entity, &Team), (With<Player>, Without<DeadBody>, Without<SoulShifting>)>()
+            .iter(&self.app.world)
+            .find(|(_, fighter_team)| **fighter_team == team)
+            .map(|(fighter, _)| fighter)
+    }
+
+    pub fn boss(&mut self) -> Entity {
+        self.app
+            .world
+            .query_filtered::<Entity, With<Boss>>()
+            .single(&self.app.world)
+    }
+
+    pub fn crowd(&mut self) -> Vec<(Entity, Vec3)> {
+        self.app
+            .world
+            .query_filtered::<(Entity, &Transform), With<CrowdMember>>()
+            .iter(&self.app.world)
+            .map(|(entity, transform)| (entity, transform.translation))
+            .collect()
+    }
+
+    pub fn get<C: Component>(&self, entity: Entity) -> Option<&C> {
+        self.app.world.get::<C>(entity)
+    }
+
+    pub fn get_mut<C: Component>(&mut self, entity: Entity) -> Option<Mut<'_, C>> {
+        self.app.world.get_mut::<C>(entity)
+    }
+
+    pub fn hp(&self, entity: Entity) -> i32 {
+        self.get::<Hp>(entity).expect("no hp").current
+    }
+
+    pub fn state(&self, entity: Entity) -> CharacterState {
+        self.get::<CharacterState>(entity)
+            .expect("no state")
+            .clone()
+    }
+
+    pub fn translation(&self, entity: Entity) -> Vec3 {
+        self.get::<Transform>(entity)
+            .expect("no transform")
+            .translation
+    }
+
+    pub fn possession_count(&self) -> i32 {
+        self.app.world.resource::<PossesionCount>().0
+    }
+}
+
+/// Read the animations from the assets folder and store them
+/// where the `AssetServer` would have.
+fn stub_animations(app: &mut App, path: &str) -> Handle<SpriteAnimations> {
+    let file = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("assets")
+        .join(path);
+    let bytes = std::fs::read(&file).unwrap_or_else(|e| panic!("{:?}: {}", file, e));
+    let animations: SpriteAnimations =
+        ron::de::from_bytes(&bytes).unwrap_or_else(|e| panic!("{:?}: {}", file, e));
+    animations
+        .validate()
+        .unwrap_or_else(|e| panic!("{:?}: {}", file, e));
+
+    let handle = app
+        .world
+        .resource::<AssetServer>()
+        .get_handle::<SpriteAnimations, _>(path);
+    app.world
+        .resource_mut::<Assets<SpriteAnimations>>()
+        .set(handle, animations)
+}
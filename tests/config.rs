@@ -0,0 +1,64 @@
+mod common;
+
+use std::path::Path;
+
+use common::Simulation;
+use grandstand_gauntlet::{
+    characters::{aggression::AttackHitbox, movement::Speed},
+    config::{CrowdConfig, GameplayConfig},
+    constants::config::GAMEPLAY_CONFIG,
+    crowd::GrandstandRow,
+};
+
+#[test]
+fn the_config_file_is_valid() {
+    let file = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("assets")
+        .join(GAMEPLAY_CONFIG);
+    let text = std::fs::read_to_string(&file).unwrap_or_else(|e| panic!("{:?}: {}", file, e));
+
+    ron::from_str::<GameplayConfig>(&text).unwrap_or_else(|e| panic!("{:?}: {}", file, e));
+}
+
+#[test]
+fn a_saved_config_is_applied_to_the_fight() {
+    let mut sim = Simulation::new();
+    let player = sim.player().expect("no player");
+    let boss = sim.boss();
+    let crowd = sim.crowd();
+
+    let mut config = GameplayConfig::default();
+    config.player.hp = 40;
+    config.player.speed = 80.;
+    config.boss.hp = 500;
+    config.boss.attack_damage = 25;
+    config.crowd = CrowdConfig {
+        row_density: vec![1.],
+        seats_by_row: 48,
+    };
+    sim.save_config(config);
+    sim.run(2);
+
+    // Full health, with the new maximum
+    assert_eq!(sim.hp(player), 40);
+    assert_eq!(sim.hp(boss), 500);
+    assert_eq!(sim.get::<Speed>(player).unwrap().0, 80.);
+
+    let damages: Vec<i32> = sim
+        .app
+        .world
+        .query::<&AttackHitbox>()
+        .iter(&sim.app.world)
+        .map(|attack_hitbox| attack_hitbox.0)
+        .collect();
+    assert_eq!(damages.iter().filter(|damage| **damage == 25).count(), 3);
+    assert_eq!(damages.iter().filter(|damage| **damage == 10).count(), 2);
+
+    // A new crowd, on a single full row: 48 seats, minus 4 aisles
+    let new_crowd = sim.crowd();
+    assert!(new_crowd.iter().all(|member| !crowd.contains(member)));
+    assert_eq!(new_crowd.len(), 44);
+    assert!(new_crowd
+        .iter()
+        .all(|(member, _)| **sim.get::<GrandstandRow>(*member).unwrap() == 0));
+}
@@ -0,0 +1,48 @@
+mod common;
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use common::Simulation;
+use grandstand_gauntlet::{
+    constants::debug::*,
+    debug::{simulation_keys, toggle_overlay},
+    simulation::{SimulationControl, SimulationTick},
+};
+
+#[test]
+fn the_overlay_key_shows_and_hides_the_overlay() {
+    let mut sim = Simulation::new();
+    sim.app
+        .insert_resource(DebugRenderContext {
+            enabled: false,
+            ..default()
+        })
+        .add_system(toggle_overlay);
+
+    sim.tap(OVERLAY_TOGGLE_KEY);
+    sim.step();
+    assert!(sim.app.world.resource::<DebugRenderContext>().enabled);
+
+    sim.tap(OVERLAY_TOGGLE_KEY);
+    sim.step();
+    assert!(!sim.app.world.resource::<DebugRenderContext>().enabled);
+}
+
+#[test]
+fn the_pause_and_step_keys_control_the_ticks() {
+    let mut sim = Simulation::new();
+    sim.app.add_system(simulation_keys);
+    let tick = |sim: &Simulation| **sim.app.world.resource::<SimulationTick>();
+
+    sim.tap(PAUSE_KEY);
+    assert!(sim.app.world.resource::<SimulationControl>().paused);
+    let paused_at = tick(&sim);
+    sim.run(5);
+    assert_eq!(tick(&sim), paused_at);
+
+    // One tick, at the frame after the press
+    sim.tap(STEP_KEY);
+    sim.run(5);
+    assert_eq!(tick(&sim), paused_at + 1);
+}
@@ -0,0 +1,264 @@
+mod common;
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use common::Simulation;
+use grandstand_gauntlet::{
+    characters::{
+        aggression::{
+            AttackHitbox, AttackHitboxKind, ComboPenalty, DamageHitEvent, Hp, Invulnerable,
+        },
+        animations::{AnimationFrameEvent, CharacterState},
+        player::PlayerDeathEvent,
+    },
+    collisions::CORPSE_GROUP,
+    constants::character::boss::BOSS_HP,
+    soul_shift::SoulShiftEvent,
+};
+
+#[test]
+fn two_boss_smashes_kill_the_player_and_shift_to_the_nearest_crowd_member() {
+    let mut sim = Simulation::new();
+    sim.record::<DamageHitEvent>()
+        .record::<SoulShiftEvent>()
+        .record::<PlayerDeathEvent>();
+
+    let first_player = sim.player().expect("no player");
+    let boss = sim.boss();
+    let crowd = sim.crowd();
+    assert!(!crowd.is_empty());
+    assert_eq!(sim.possession_count(), 1);
+
+    // The player stands still, right under the boss
+    let mut last_position = sim.translation(first_player);
+    let died = sim.run_until(60 * 60, |sim| {
+        if sim.hp(first_player) > 0 {
+            last_position = sim.translation(first_player);
+            false
+        } else {
+            true
+        }
+    });
+    assert!(died, "the boss never killed the player");
+
+    let hits: Vec<_> = sim
+        .recorded::<DamageHitEvent>()
+        .iter()
+        .map(|(_, hit)| (hit.attacker, hit.target))
+        .collect();
+    assert_eq!(hits, vec![(boss, first_player), (boss, first_player)]);
+
+    assert!(sim.run_until(5, |sim| sim.possession_count() == 2));
+    assert_eq!(sim.recorded::<SoulShiftEvent>().len(), 1);
+    assert_eq!(sim.recorded::<SoulShiftEvent>()[0].1 .0, first_player);
+    assert_eq!(sim.recorded::<PlayerDeathEvent>()[0].1 .0, first_player);
+    assert_eq!(sim.state(first_player), CharacterState::Dead);
+
+    // The soul travels to the new body
+    assert!(sim.run_until(60 * 10, |sim| sim.player().is_some()));
+    let new_player = sim.player().unwrap();
+
+    let nearest = crowd
+        .iter()
+        .min_by(|(_, a), (_, b)| {
+            let a = last_position.truncate().distance(a.truncate());
+            let b = last_position.truncate().distance(b.truncate());
+            a.total_cmp(&b)
+        })
+        .map(|(member, _)| *member)
+        .unwrap();
+    assert_eq!(new_player, nearest);
+    assert_eq!(sim.hp(new_player), 20);
+    assert_eq!(sim.hp(boss), BOSS_HP);
+}
+
+#[test]
+fn a_light_attack_hits_the_boss() {
+    let mut sim = Simulation::new();
+    sim.record::<DamageHitEvent>();
+
+    let player = sim.player().expect("no player");
+    let boss = sim.boss();
+
+    sim.tap(KeyCode::Return);
+    sim.step();
+    assert_eq!(sim.state(player), CharacterState::Attack);
+
+    assert!(sim.run_until(60, |sim| sim.hp(boss) < BOSS_HP));
+    let hits = sim.recorded::<DamageHitEvent>();
+    assert_eq!(hits.len(), 1);
+    assert_eq!((hits[0].1.attacker, hits[0].1.target), (player, boss));
+}
+
+/// The first attack hitbox activated, if any
+fn active_attack_hitbox(sim: &mut Simulation) -> Option<Entity> {
+    sim.app
+        .world
+        .query_filtered::<Entity, (With<AttackHitbox>, With<ActiveEvents>)>()
+        .iter(&sim.app.world)
+        .next()
+}
+
+#[test]
+fn an_attack_hits_once_the_target_is_vulnerable_again() {
+    let mut sim = Simulation::new();
+    sim.record::<DamageHitEvent>();
+    let boss = sim.boss();
+    sim.app
+        .world
+        .entity_mut(boss)
+        .insert(Invulnerable(Timer::from_seconds(60., TimerMode::Once)));
+
+    sim.tap(KeyCode::Return);
+    assert!(sim.run_until(60, |sim| active_attack_hitbox(sim).is_some()));
+    let hitbox = active_attack_hitbox(&mut sim).unwrap();
+    // The overlap has started
+    sim.run(2);
+    assert!(sim.recorded::<DamageHitEvent>().is_empty());
+
+    // Still in the same swing
+    sim.app.world.entity_mut(boss).remove::<Invulnerable>();
+    sim.step();
+    assert_eq!(active_attack_hitbox(&mut sim), Some(hitbox));
+    let hits = sim.recorded::<DamageHitEvent>();
+    assert_eq!(hits.len(), 1);
+    assert_eq!((hits[0].1.attack_hitbox, hits[0].1.target), (hitbox, boss));
+}
+
+#[test]
+fn a_dropped_combo_is_penalized() {
+    let mut sim = Simulation::new();
+    let player = sim.player().expect("no player");
+
+    sim.tap(KeyCode::Return);
+    sim.step();
+    // Pressed again during the first swing
+    sim.tap(KeyCode::Return);
+    assert!(sim.run_until(60, |sim| sim.state(player) == CharacterState::SecondAttack));
+    assert!(sim.get::<ComboPenalty>(player).is_none());
+
+    // The slap return could lead to another swing
+    assert!(sim.run_until(60, |sim| sim.state(player) != CharacterState::SecondAttack));
+    sim.step();
+    assert!(sim.get::<ComboPenalty>(player).is_some());
+}
+
+#[test]
+fn the_player_can_give_up_their_body() {
+    let mut sim = Simulation::new();
+    sim.record::<SoulShiftEvent>();
+
+    let first_player = sim.player().expect("no player");
+
+    sim.tap(KeyCode::E);
+    sim.step();
+
+    assert_eq!(sim.hp(first_player), 0);
+    assert_eq!(sim.recorded::<SoulShiftEvent>().len(), 1);
+    assert_eq!(sim.possession_count(), 2);
+    assert!(sim.player().is_none());
+
+    assert!(sim.run_until(60 * 10, |sim| sim.player().is_some()));
+    assert_ne!(sim.player(), Some(first_player));
+}
+
+#[test]
+fn a_corpse_keeps_its_hurtbox_out_of_the_fight() {
+    let mut sim = Simulation::new();
+    let corpse = sim.player().expect("no player");
+
+    sim.tap(KeyCode::E);
+    sim.run(5);
+
+    let children = sim.get::<Children>(corpse).unwrap().to_vec();
+    assert_eq!(children.len(), 1);
+    assert!(sim.get::<Collider>(children[0]).is_some());
+    assert!(sim.get::<AttackHitbox>(children[0]).is_none());
+    assert_eq!(
+        sim.get::<CollisionGroups>(children[0]).copied(),
+        Some(CollisionGroups::new(CORPSE_GROUP, Group::NONE))
+    );
+}
+
+#[test]
+fn the_first_frame_of_the_hit_and_death_clips_sends_its_events() {
+    let mut sim = Simulation::new();
+    sim.record::<AnimationFrameEvent>()
+        .record::<PlayerDeathEvent>();
+    let player = sim.player().expect("no player");
+
+    // The player stands still, right under the boss
+    assert!(sim.run_until(60 * 60, |sim| {
+        !sim.recorded::<PlayerDeathEvent>().is_empty()
+    }));
+    sim.run(5);
+
+    let events: Vec<_> = sim
+        .recorded::<AnimationFrameEvent>()
+        .iter()
+        .filter(|(_, event)| event.entity == player)
+        .map(|(_, event)| (event.state.clone(), event.name.as_str()))
+        .collect();
+    assert!(events.contains(&(CharacterState::Hit, "sfx:hurt")));
+    assert!(events.contains(&(CharacterState::Dead, "sfx:death")));
+}
+
+#[test]
+fn the_same_seed_gives_the_same_fight() {
+    let mut fights = [Simulation::with_seed(7), Simulation::with_seed(7)];
+
+    let [crowd_a, crowd_b] = fights.each_mut().map(|sim| sim.crowd());
+    assert_eq!(crowd_a.len(), crowd_b.len());
+    for ((_, a), (_, b)) in crowd_a.iter().zip(crowd_b.iter()) {
+        assert_eq!(a, b);
+    }
+
+    let [death_a, death_b] = fights.each_mut().map(|sim| {
+        sim.record::<PlayerDeathEvent>();
+        sim.tap(KeyCode::Return);
+        sim.run_until(60 * 60, |sim| {
+            !sim.recorded::<PlayerDeathEvent>().is_empty()
+        });
+        let boss = sim.boss();
+        (sim.tick(), sim.hp(boss))
+    });
+    assert_eq!(death_a, death_b);
+}
+
+#[test]
+fn both_smash_hitboxes_only_hit_the_player_once() {
+    let mut sim = Simulation::new();
+    sim.record::<SoulShiftEvent>();
+    let player = sim.player().expect("no player");
+    let boss = sim.boss();
+    // Out of the invulnerability of the fresh body
+    sim.app.world.entity_mut(player).remove::<Invulnerable>();
+    sim.get_mut::<Hp>(player).unwrap().current = 1;
+
+    let smash_hitboxes: Vec<Entity> = sim
+        .app
+        .world
+        .query::<(Entity, &AttackHitboxKind)>()
+        .iter(&sim.app.world)
+        .filter(|(_, kind)| **kind == AttackHitboxKind::Smash)
+        .map(|(hitbox, _)| hitbox)
+        .collect();
+    assert_eq!(smash_hitboxes.len(), 2);
+
+    // Touched on the same tick
+    for attack_hitbox in smash_hitboxes {
+        sim.app
+            .world
+            .resource_mut::<Events<DamageHitEvent>>()
+            .send(DamageHitEvent {
+                attack_hitbox,
+                attacker: boss,
+                target: player,
+            });
+    }
+    sim.step();
+
+    assert_eq!(sim.hp(player), 0);
+    assert_eq!(sim.recorded::<SoulShiftEvent>().len(), 1);
+}
@@ -0,0 +1,129 @@
+mod common;
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use common::Simulation;
+use grandstand_gauntlet::{
+    characters::{
+        aggression::{AttackHitboxKind, AttackTargets, ComboPenalty, Hp},
+        animations::CharacterState,
+        moveset::Moveset,
+        npcs::boss::Boss,
+    },
+    collisions::{BOSS_HURT_GROUP, PLAYER_HURT_GROUP},
+    constants::character::boss::BOSS_HP,
+    soul_shift::SoulShiftEvent,
+};
+
+/// Kills the boss with a light attack, and waits for the new body
+fn possess_the_boss(sim: &mut Simulation) -> (Entity, Entity) {
+    let killer = sim.player().expect("no player");
+    let boss = sim.boss();
+    sim.get_mut::<Hp>(boss).unwrap().current = 1;
+
+    sim.tap(KeyCode::Return);
+    assert!(sim.run_until(60 * 10, |sim| sim.player() == Some(boss)));
+    (killer, boss)
+}
+
+/// The attack hitboxes of the character: their kind and their targets
+fn attack_hitboxes(sim: &mut Simulation, character: Entity) -> Vec<(AttackHitboxKind, Group)> {
+    let mut hitboxes = Vec::new();
+    let sensors = sim.get::<Children>(character).unwrap().to_vec();
+    for sensor in sensors {
+        for hitbox in sim.get::<Children>(sensor).into_iter().flatten() {
+            if let (Some(kind), Some(targets)) = (
+                sim.get::<AttackHitboxKind>(*hitbox),
+                sim.get::<AttackTargets>(*hitbox),
+            ) {
+                hitboxes.push((*kind, **targets));
+            }
+        }
+    }
+    hitboxes
+}
+
+#[test]
+fn the_killer_of_the_boss_takes_their_body() {
+    let mut sim = Simulation::new();
+    sim.record::<SoulShiftEvent>();
+
+    let (killer, boss) = possess_the_boss(&mut sim);
+
+    assert_eq!(sim.hp(killer), 0);
+    assert_eq!(sim.recorded::<SoulShiftEvent>().len(), 1);
+    assert_eq!(sim.possession_count(), 2);
+    assert!(sim.get::<Boss>(boss).is_none());
+    assert_eq!(sim.get::<Moveset>(boss), Some(&Moveset::Boss));
+    assert_eq!(sim.hp(boss), BOSS_HP);
+}
+
+#[test]
+fn the_possessed_boss_keeps_their_own_hitboxes() {
+    let mut sim = Simulation::new();
+    let (_, boss) = possess_the_boss(&mut sim);
+
+    let mut kinds: Vec<_> = attack_hitboxes(&mut sim, boss)
+        .into_iter()
+        .map(|(kind, targets)| {
+            // Now on the player's side
+            assert_eq!(targets, BOSS_HURT_GROUP);
+            kind
+        })
+        .collect();
+    kinds.sort_by_key(|kind| format!("{:?}", kind));
+    assert_eq!(
+        kinds,
+        [
+            AttackHitboxKind::FallenAngel,
+            AttackHitboxKind::Smash,
+            AttackHitboxKind::Smash
+        ]
+    );
+
+    let hurtbox_groups = sim
+        .get::<Children>(boss)
+        .unwrap()
+        .iter()
+        .find_map(|child| {
+            sim.get::<CollisionGroups>(*child)
+                .filter(|_| sim.get::<AttackTargets>(*child).is_none())
+        })
+        .copied();
+    assert_eq!(
+        hurtbox_groups.map(|groups| groups.memberships),
+        Some(PLAYER_HURT_GROUP)
+    );
+}
+
+#[test]
+fn the_possessed_boss_chains_the_smash_into_the_fallen_angel() {
+    let mut sim = Simulation::new();
+    let (_, boss) = possess_the_boss(&mut sim);
+
+    sim.tap(KeyCode::Return);
+    sim.step();
+    assert_eq!(sim.state(boss), CharacterState::Attack);
+
+    // Pressed again during the backhand
+    sim.tap(KeyCode::Return);
+    assert!(sim.run_until(60, |sim| sim.state(boss) == CharacterState::SecondAttack));
+}
+
+#[test]
+fn the_fallen_angel_ends_the_combo_without_penalty() {
+    let mut sim = Simulation::new();
+    let (_, boss) = possess_the_boss(&mut sim);
+
+    sim.tap(KeyCode::Return);
+    sim.step();
+    sim.tap(KeyCode::Return);
+    assert!(sim.run_until(60, |sim| sim.state(boss) == CharacterState::SecondAttack));
+
+    // The last swing of the chain
+    assert!(sim.run_until(60 * 2, |sim| sim.state(boss)
+        != CharacterState::SecondAttack));
+    sim.step();
+    assert!(sim.get::<ComboPenalty>(boss).is_none());
+}
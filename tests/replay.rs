@@ -0,0 +1,119 @@
+mod common;
+
+use bevy::prelude::*;
+
+use common::Simulation;
+use grandstand_gauntlet::{
+    config::GameplayConfig,
+    replay::{GameMode, Replay, ReplayError, ReplayPlugin},
+    training::Training,
+};
+
+/// Walk to the boss, attack, dash away, charge an attack, then wait.
+fn play_a_fight(sim: &mut Simulation) {
+    sim.press(KeyCode::Right);
+    sim.run(20);
+    sim.release(KeyCode::Right);
+    sim.tap(KeyCode::Return);
+    sim.run(30);
+    sim.tap(KeyCode::LShift);
+    sim.run(10);
+    sim.press(KeyCode::Return);
+    sim.run(40);
+    sim.release(KeyCode::Return);
+    sim.run(120);
+}
+
+#[test]
+fn a_replay_reproduces_the_fight() {
+    let mut original = Simulation::new();
+    play_a_fight(&mut original);
+
+    let recording = original.recording();
+    assert!(!recording.inputs.is_empty());
+
+    // Through the file format
+    let text = ron::to_string(&recording).unwrap();
+    let replay: Replay = ron::from_str(&text).unwrap();
+    assert_eq!(replay, recording);
+
+    let mut replayed = Simulation::replay(replay);
+    replayed.run_until(60 * 10, |sim| sim.tick() == original.tick());
+
+    let player = original.player().expect("no player");
+    let boss = original.boss();
+    assert_eq!(replayed.player(), Some(player));
+    assert_eq!(replayed.boss(), boss);
+    for entity in [player, boss] {
+        assert_eq!(replayed.hp(entity), original.hp(entity));
+        assert_eq!(replayed.state(entity), original.state(entity));
+        assert_eq!(replayed.translation(entity), original.translation(entity));
+    }
+    assert_eq!(replayed.recording(), recording);
+}
+
+#[test]
+fn the_devices_are_ignored_during_a_replay() {
+    let mut original = Simulation::new();
+    let player = original.player().expect("no player");
+    original.press(KeyCode::Right);
+    original.run(40);
+    original.release(KeyCode::Right);
+    original.step();
+
+    let mut replayed = Simulation::replay(original.recording());
+    replayed.press(KeyCode::Left);
+    replayed.run_until(60, |sim| sim.tick() == original.tick());
+
+    assert_eq!(replayed.translation(player), original.translation(player));
+}
+
+#[test]
+fn a_replay_is_played_in_its_mode_and_with_its_config() {
+    let mut original = Simulation::training(Training::default());
+    original.run(10);
+    original
+        .app
+        .world
+        .resource_mut::<GameplayConfig>()
+        .player
+        .speed *= 2.;
+    play_a_fight(&mut original);
+
+    let recording = original.recording();
+    assert_eq!(recording.mode, GameMode::Training(Training::default()));
+    assert_eq!(recording.configs.len(), 2);
+
+    let mut replayed = Simulation::replay(recording);
+    assert!(replayed.app.world.contains_resource::<Training>());
+    replayed.run_until(60 * 10, |sim| sim.tick() == original.tick());
+
+    let player = original.player().expect("no player");
+    assert_eq!(replayed.translation(player), original.translation(player));
+    assert_eq!(
+        replayed.app.world.resource::<GameplayConfig>(),
+        original.app.world.resource::<GameplayConfig>()
+    );
+}
+
+fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+#[test]
+fn the_replay_arguments_leave_the_others_alone() {
+    let plugin =
+        ReplayPlugin::parse_args(args(&["--versus", "--record", "fight.ron", "--training"]))
+            .unwrap();
+    assert_eq!(plugin.record_to, Some("fight.ron".into()));
+    assert!(plugin.replay.is_none());
+
+    assert!(matches!(
+        ReplayPlugin::parse_args(args(&["--training", "--record"])),
+        Err(ReplayError::MissingPath(_))
+    ));
+    assert!(matches!(
+        ReplayPlugin::parse_args(args(&["--replay", "missing.replay.ron"])),
+        Err(ReplayError::Io(_))
+    ));
+}
@@ -0,0 +1,69 @@
+mod common;
+
+use bevy::prelude::*;
+
+use common::Simulation;
+use grandstand_gauntlet::simulation::{SimulationControl, TimeScale};
+
+/// Presses of the key seen by a frame system
+#[derive(Resource, Default)]
+struct SeenPresses(u32);
+
+fn count_presses(keyboard_input: Res<Input<KeyCode>>, mut seen: ResMut<SeenPresses>) {
+    if keyboard_input.just_pressed(KeyCode::F5) {
+        seen.0 += 1;
+    }
+}
+
+fn control(sim: &mut Simulation) -> Mut<'_, SimulationControl> {
+    sim.app.world.resource_mut::<SimulationControl>()
+}
+
+#[test]
+fn a_paused_simulation_plays_one_tick_per_step() {
+    let mut sim = Simulation::new();
+    let player = sim.player().expect("no player");
+    let tick = sim.tick();
+
+    control(&mut sim).paused = true;
+    sim.press(KeyCode::Right);
+    sim.run(30);
+    assert_eq!(sim.tick(), tick);
+    let position = sim.translation(player);
+
+    // The key held during the pause is read by the stepped tick
+    control(&mut sim).step();
+    sim.run(30);
+    assert_eq!(sim.tick(), tick + 1);
+    assert!(sim.translation(player).x > position.x);
+
+    control(&mut sim).paused = false;
+    sim.run(10);
+    assert_eq!(sim.tick(), tick + 11);
+}
+
+#[test]
+fn a_slowed_down_simulation_plays_fewer_ticks() {
+    let mut sim = Simulation::new();
+    let tick = sim.tick();
+
+    control(&mut sim).time_scale = TimeScale::Half;
+    sim.run(20);
+    assert_eq!(sim.tick(), tick + 10);
+
+    control(&mut sim).time_scale = TimeScale::Quarter;
+    sim.run(20);
+    assert_eq!(sim.tick(), tick + 15);
+}
+
+#[test]
+fn the_frame_systems_see_the_inputs_read_by_the_ticks() {
+    let mut sim = Simulation::new();
+    sim.app
+        .init_resource::<SeenPresses>()
+        .add_system(count_presses);
+
+    sim.tap(KeyCode::F5);
+    sim.run(5);
+    assert_eq!(sim.app.world.resource::<SeenPresses>().0, 1);
+}
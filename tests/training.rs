@@ -0,0 +1,166 @@
+mod common;
+
+use bevy::prelude::*;
+
+use common::Simulation;
+use grandstand_gauntlet::{
+    characters::{
+        aggression::{DamageDealtEvent, DamageHitEvent, Hp},
+        animations::CharacterState,
+        npcs::boss::BossBehaviour,
+    },
+    combat_log::{CombatEvent, CombatLog},
+    constants::training::RESET_KEY,
+    training::{DamageNumber, Training},
+};
+
+#[test]
+fn an_idle_boss_never_attacks() {
+    let mut sim = Simulation::training(Training::default());
+    sim.record::<DamageHitEvent>();
+    let boss = sim.boss();
+
+    // The player stands still, right under the boss
+    assert!(!sim.run_until(60 * 10, |sim| sim.state(boss) == CharacterState::Attack));
+    assert!(sim.recorded::<DamageHitEvent>().is_empty());
+}
+
+#[test]
+fn with_infinite_hp_the_player_takes_the_hits_without_dying() {
+    let mut sim = Simulation::training(Training {
+        boss: BossBehaviour::Loop(CharacterState::Attack),
+        ..default()
+    });
+    sim.record::<DamageHitEvent>();
+    let player = sim.player().expect("no player");
+
+    // Way more than the two lethal smashes
+    assert!(sim.run_until(60 * 60, |sim| sim.recorded::<DamageHitEvent>().len() >= 4));
+    assert_eq!(sim.player(), Some(player));
+    assert_eq!(sim.possession_count(), 1);
+    assert!(sim.hp(player) > 0);
+}
+
+#[test]
+fn a_looped_attack_is_played_again_and_again() {
+    let mut sim = Simulation::training(Training {
+        boss: BossBehaviour::Loop(CharacterState::SecondAttack),
+        ..default()
+    });
+    let boss = sim.boss();
+
+    let attacks = |sim: &Simulation| {
+        sim.app
+            .world
+            .resource::<CombatLog>()
+            .entries
+            .iter()
+            .filter(|entry| {
+                matches!(
+                    entry.event,
+                    CombatEvent::StateChange { entity, to: CharacterState::SecondAttack, .. }
+                        if entity == boss
+                )
+            })
+            .count()
+    };
+    assert!(sim.run_until(60 * 20, |sim| attacks(sim) >= 3));
+}
+
+#[test]
+fn a_free_soul_shift_leaves_the_grandstand_full() {
+    let mut sim = Simulation::training(Training::default());
+    let crowd_size = sim.crowd().len();
+
+    sim.tap(KeyCode::E);
+    assert!(sim.run_until(5, |sim| sim.possession_count() == 2));
+    sim.run(1);
+    assert_eq!(sim.crowd().len(), crowd_size);
+
+    // Without it, the seat stays empty
+    let mut sim = Simulation::training(Training {
+        free_soul_shift: false,
+        ..default()
+    });
+    sim.tap(KeyCode::E);
+    assert!(sim.run_until(5, |sim| sim.possession_count() == 2));
+    sim.run(1);
+    assert_eq!(sim.crowd().len(), crowd_size - 1);
+}
+
+#[test]
+fn a_reset_brings_back_the_start_of_the_fight() {
+    let mut sim = Simulation::training(Training::default());
+    let player = sim.player().expect("no player");
+    let start = sim.translation(player);
+
+    sim.get_mut::<Hp>(player).unwrap().current = 5;
+    sim.press(KeyCode::D);
+    sim.run(30);
+    sim.release(KeyCode::D);
+    assert_ne!(sim.translation(player), start);
+
+    sim.app.world.resource_mut::<Training>().reset = true;
+    sim.run(1);
+    assert_eq!(sim.hp(player), 20);
+    assert_eq!(sim.translation(player).truncate(), start.truncate());
+    assert!(!sim.app.world.resource::<Training>().reset);
+}
+
+#[test]
+fn the_reset_key_brings_back_full_health() {
+    let mut sim = Simulation::training(Training::default());
+    let player = sim.player().expect("no player");
+
+    sim.get_mut::<Hp>(player).unwrap().current = 5;
+    sim.tap(RESET_KEY);
+    sim.run(2);
+    assert_eq!(sim.hp(player), 20);
+}
+
+#[test]
+fn a_damage_number_pops_for_each_damage_dealt() {
+    let mut sim = Simulation::training(Training::default());
+    sim.record::<DamageDealtEvent>();
+
+    sim.tap(KeyCode::Return);
+    assert!(sim.run_until(60, |sim| !sim.recorded::<DamageDealtEvent>().is_empty()));
+
+    let damage = sim.recorded::<DamageDealtEvent>()[0].1.damage;
+    let numbers: Vec<String> = sim
+        .app
+        .world
+        .query_filtered::<&Text, With<DamageNumber>>()
+        .iter(&sim.app.world)
+        .map(|text| text.sections[0].value.clone())
+        .collect();
+    assert_eq!(numbers, vec![damage.to_string()]);
+}
+
+#[test]
+fn a_lethal_hit_on_an_undying_target_logs_the_refilled_hp() {
+    let mut sim = Simulation::training(Training::default());
+    let boss = sim.boss();
+    sim.get_mut::<Hp>(boss).unwrap().current = 1;
+
+    sim.tap(KeyCode::Return);
+    let hit = |sim: &Simulation| {
+        sim.app
+            .world
+            .resource::<CombatLog>()
+            .hits()
+            .find_map(|entry| match entry.event {
+                CombatEvent::Hit {
+                    target,
+                    hp_before,
+                    hp_after,
+                    ..
+                } if target == boss => Some((hp_before, hp_after)),
+                _ => None,
+            })
+    };
+    assert!(sim.run_until(60, |sim| hit(sim).is_some()));
+
+    assert!(sim.hp(boss) > 1);
+    assert_eq!(hit(&sim), Some((1, sim.hp(boss))));
+}
@@ -0,0 +1,185 @@
+mod common;
+
+use bevy::prelude::*;
+
+use common::Simulation;
+use grandstand_gauntlet::{
+    characters::{aggression::Invulnerable, input_map::InputMap, npcs::boss::Boss},
+    collisions::Team,
+    combat_log::{CombatEvent, CombatLog},
+    constants::{crowd::CROWD_SCROLL, versus::PLAYER_TWO_TINT},
+    crowd::{CrowdMember, GrandstandRow},
+    game_feel::{GameFeelPlugin, HitFlash},
+    versus::{Rounds, Versus},
+};
+
+fn fighters(sim: &mut Simulation) -> (Entity, Entity) {
+    (
+        sim.fighter(Team::Player).expect("no first player"),
+        sim.fighter(Team::Boss).expect("no second player"),
+    )
+}
+
+#[test]
+fn two_players_face_each_other_without_the_boss() {
+    let mut sim = Simulation::versus(Versus::default());
+    let (one, two) = fighters(&mut sim);
+
+    assert!(sim
+        .app
+        .world
+        .query_filtered::<Entity, With<Boss>>()
+        .iter(&sim.app.world)
+        .next()
+        .is_none());
+    assert!(sim.translation(one).x < sim.translation(two).x);
+    assert_eq!(sim.get::<InputMap>(one), Some(&InputMap::PLAYER_ONE));
+    assert_eq!(sim.get::<InputMap>(two), Some(&InputMap::PLAYER_TWO));
+}
+
+#[test]
+fn each_player_has_their_own_keys() {
+    let mut sim = Simulation::versus(Versus::default());
+    let (one, two) = fighters(&mut sim);
+    let (start_one, start_two) = (sim.translation(one), sim.translation(two));
+
+    // The first player backs off
+    sim.press(KeyCode::A);
+    sim.run(20);
+    sim.release(KeyCode::A);
+    assert!(sim.translation(one).x < start_one.x);
+    assert_eq!(sim.translation(two).x, start_two.x);
+
+    // The second one too
+    sim.press(KeyCode::Right);
+    sim.run(20);
+    sim.release(KeyCode::Right);
+    assert!(sim.translation(two).x > start_two.x);
+}
+
+#[test]
+fn the_players_hit_each_other() {
+    let mut sim = Simulation::versus(Versus::default());
+    let (one, two) = fighters(&mut sim);
+
+    // Next to each other, once the spawn invulnerability is over
+    sim.press(KeyCode::D);
+    assert!(sim.run_until(60 * 5, |sim| {
+        sim.translation(two).x - sim.translation(one).x < 20.
+    }));
+    sim.release(KeyCode::D);
+    sim.run_for(10.);
+
+    sim.tap(KeyCode::Space);
+    let hit = |sim: &mut Simulation| {
+        sim.app.world.resource::<CombatLog>().hits().any(|entry| {
+            matches!(
+                entry.event,
+                CombatEvent::Hit { attacker, target, .. } if attacker == one && target == two
+            )
+        })
+    };
+    assert!(sim.run_until(60, hit));
+    assert!(sim.hp(two) < sim.hp(one));
+}
+
+#[test]
+fn a_death_wins_the_round_and_the_loser_comes_back_from_the_crowd() {
+    let mut sim = Simulation::versus(Versus::default());
+    let (_, two) = fighters(&mut sim);
+    let crowd_size = sim.crowd().len();
+
+    // The second player gives up their body
+    sim.tap(KeyCode::Back);
+    sim.run(1);
+    let rounds = sim.app.world.resource::<Rounds>().clone();
+    assert_eq!(rounds.round_winner, Some(Team::Player));
+    assert_eq!(rounds.wins(Team::Player), 1);
+    assert_eq!(rounds.match_winner, None);
+
+    // Their soul shifts to the shared grandstand
+    assert!(sim.run_until(60 * 10, |sim| sim.fighter(Team::Boss).is_some()));
+    let new_two = sim.fighter(Team::Boss).unwrap();
+    assert_ne!(new_two, two);
+    assert_eq!(sim.crowd().len(), crowd_size - 1);
+    assert_eq!(sim.get::<InputMap>(new_two), Some(&InputMap::PLAYER_TWO));
+
+    sim.run(1);
+    let rounds = sim.app.world.resource::<Rounds>().clone();
+    assert_eq!(rounds.round, 2);
+    assert_eq!(rounds.round_winner, None);
+    let (one, two) = fighters(&mut sim);
+    assert!(sim.translation(one).x < sim.translation(two).x);
+}
+
+#[test]
+fn a_new_match_starts_after_the_last_round() {
+    let mut sim = Simulation::versus(Versus { rounds_to_win: 1 });
+
+    // The first player gives up their body
+    sim.tap(KeyCode::E);
+    sim.run(1);
+    let rounds = sim.app.world.resource::<Rounds>().clone();
+    assert_eq!(rounds.match_winner, Some(Team::Boss));
+
+    assert!(sim.run_until(60 * 10, |sim| sim.fighter(Team::Player).is_some()));
+    sim.run(1);
+    let rounds = sim.app.world.resource::<Rounds>().clone();
+    assert_eq!(rounds.round, 1);
+    assert_eq!(rounds.match_winner, None);
+    assert_eq!(rounds.wins(Team::Boss), 0);
+}
+
+#[test]
+fn the_second_player_keeps_their_tint_after_a_flash() {
+    let mut sim = Simulation::versus(Versus::default());
+    sim.app.add_plugin(GameFeelPlugin);
+    let (one, two) = fighters(&mut sim);
+
+    sim.press(KeyCode::D);
+    assert!(sim.run_until(60 * 5, |sim| {
+        sim.translation(two).x - sim.translation(one).x < 20.
+    }));
+    sim.release(KeyCode::D);
+    sim.run_for(10.);
+
+    sim.tap(KeyCode::Space);
+    assert!(sim.run_until(60, |sim| sim.get::<HitFlash>(two).is_some()));
+    // Neither flashing nor blinking anymore
+    assert!(sim.run_until(60 * 5, |sim| {
+        sim.get::<HitFlash>(two).is_none() && sim.get::<Invulnerable>(two).is_none()
+    }));
+    sim.step();
+
+    assert_eq!(
+        sim.get::<TextureAtlasSprite>(two).unwrap().color,
+        PLAYER_TWO_TINT
+    );
+}
+
+#[test]
+fn the_crowd_moves_against_the_middle_of_the_fighters() {
+    let mut sim = Simulation::versus(Versus::default());
+    let (one, two) = fighters(&mut sim);
+    let front_member = sim
+        .app
+        .world
+        .query_filtered::<(Entity, &GrandstandRow), With<CrowdMember>>()
+        .iter(&sim.app.world)
+        .find(|(_, row)| row.0 == 0)
+        .map(|(member, _)| member)
+        .expect("no front row");
+    let middle = |sim: &Simulation| (sim.translation(one).x + sim.translation(two).x) / 2.;
+    let (start_middle, start_member) = (middle(&sim), sim.translation(front_member).x);
+
+    // Only the second player backs off
+    sim.press(KeyCode::Right);
+    sim.run(30);
+    sim.release(KeyCode::Right);
+    sim.run(5);
+
+    let moved = middle(&sim) - start_middle;
+    assert!(moved > 0.);
+    let scrolled = sim.translation(front_member).x - start_member;
+    assert!((scrolled + moved * CROWD_SCROLL).abs() < 0.5);
+}